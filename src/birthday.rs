@@ -0,0 +1,170 @@
+#![allow(unused)]
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use rand_core::RngCore;
+
+use crate::{
+    digest::Digest,
+    random::{os, seeded, RandomSource},
+};
+
+// the first `bits` bits of a digest
+pub fn truncate(digest: &[u8], bits: u32) -> u64 {
+    assert!((1..=64).contains(&bits) && digest.len() * 8 >= bits as usize);
+    let mut v = [0; 8];
+    let len = digest.len().min(8);
+    v[..len].copy_from_slice(&digest[..len]);
+    u64::from_be_bytes(v) >> (64 - bits)
+}
+
+// The function walked by the search: an n bit value is hashed as an input of
+// ceil(n / 8) bytes and the digest truncated back to n bits. Two values
+// stepping to the same one are a collision of the truncated hash.
+struct Walk<F> {
+    f: F,
+    bits: u32,
+}
+
+impl<F, T> Walk<F>
+where
+    F: Fn(&[u8]) -> T,
+    T: AsRef<[u8]>,
+{
+    fn input(&self, x: u64) -> Vec<u8> {
+        x.to_be_bytes()[8 - self.bits.div_ceil(8) as usize..].to_vec()
+    }
+    fn step(&self, x: u64) -> u64 {
+        truncate((self.f)(&self.input(x)).as_ref(), self.bits)
+    }
+    // two trails of `len1` and `len2` steps ending in the same point merge
+    // somewhere, the values right before that are the collision
+    fn locate(&self, mut x1: u64, len1: usize, mut x2: u64, len2: usize) -> Option<(u64, u64)> {
+        for _ in len2..len1 {
+            x1 = self.step(x1);
+        }
+        for _ in len1..len2 {
+            x2 = self.step(x2);
+        }
+        // one trail started on the other one
+        if x1 == x2 {
+            return None;
+        }
+        loop {
+            let (y1, y2) = (self.step(x1), self.step(x2));
+            if y1 == y2 {
+                return Some((x1, x2));
+            }
+            (x1, x2) = (y1, y2);
+        }
+    }
+}
+
+// Two different inputs whose digests agree on the first `bits` bits, with
+// memory for about 2^(bits / 2 - d) points rather than 2^(bits / 2).
+// Every thread walks from random values until it hits a distinguished point,
+// one with d low zero bits. Only the start, length and end of each trail are
+// kept, two trails ending in the same point contain a collision.
+pub fn find_collision<F, T>(f: F, bits: u32, threads: usize) -> (Vec<u8>, Vec<u8>)
+where
+    F: Fn(&[u8]) -> T + Sync,
+    T: AsRef<[u8]>,
+{
+    find_collision_with(&mut os(), f, bits, threads)
+}
+
+// every thread draws its starting points from a generator seeded by `rng`
+pub fn find_collision_with<F, T>(
+    rng: &mut impl RandomSource,
+    f: F,
+    bits: u32,
+    threads: usize,
+) -> (Vec<u8>, Vec<u8>)
+where
+    F: Fn(&[u8]) -> T + Sync,
+    T: AsRef<[u8]>,
+{
+    assert!(bits <= 64);
+    let walk = Walk { f, bits };
+    let mask = if bits == 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    };
+    let d = (bits / 4).max(1);
+    // a walk caught in a cycle without distinguished points is abandoned
+    let max_len = 20 << d;
+
+    let trails: Mutex<HashMap<u64, (u64, usize)>> = Mutex::new(HashMap::new());
+    let found = Mutex::new(None);
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            let mut rng = seeded(rng.next_u64());
+            let (walk, trails, found, done) = (&walk, &trails, &found, &done);
+            s.spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    let start = rng.next_u64() & mask;
+                    let mut x = start;
+                    let mut len = 0;
+                    while len < max_len {
+                        x = walk.step(x);
+                        len += 1;
+                        if x.trailing_zeros() >= d {
+                            break;
+                        }
+                    }
+                    if len == max_len {
+                        continue;
+                    }
+                    let other = trails.lock().unwrap().insert(x, (start, len));
+                    if let Some((other, other_len)) = other.filter(|(o, _)| *o != start) {
+                        if let Some((x1, x2)) = walk.locate(start, len, other, other_len) {
+                            *found.lock().unwrap() = Some((walk.input(x1), walk.input(x2)));
+                            done.store(true, Ordering::Relaxed);
+                        }
+                    }
+                }
+            });
+        }
+    });
+    found.into_inner().unwrap().unwrap()
+}
+
+pub fn find_digest_collision<D: Digest>(bits: u32, threads: usize) -> (Vec<u8>, Vec<u8>) {
+    find_collision(D::digest, bits, threads)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        md4::{md4, Md4},
+        sha1::sha1,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate(&[0xab, 0xcd, 0xef], 12), 0xabc);
+        assert_eq!(truncate(&[0xab, 0xcd, 0xef], 24), 0xabcdef);
+        assert_eq!(truncate(&[0xff; 20], 64), u64::MAX);
+    }
+
+    #[test]
+    fn test_find_collision() {
+        let (m1, m2) = find_collision_with(&mut seeded(92), sha1, 32, 2);
+        assert_ne!(m1, m2);
+        assert_eq!(truncate(&sha1(&m1), 32), truncate(&sha1(&m2), 32));
+
+        let (m1, m2) = find_digest_collision::<Md4>(36, 2);
+        assert_ne!(m1, m2);
+        assert_eq!(truncate(&md4(&m1), 36), truncate(&md4(&m2), 36));
+    }
+}
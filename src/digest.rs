@@ -0,0 +1,285 @@
+#![allow(unused)]
+use crate::{
+    md4::{self, Md4},
+    md5::{self, Md5},
+    merkle_damgard::{AesHash, Compress, MerkleDamgard, ToyHash},
+    sha1::{self, Sha1},
+    sha2::{self, Sha224, Sha256, Sha384, Sha512},
+};
+
+pub trait Digest: Clone {
+    const BLOCK_SIZE: usize;
+    const OUTPUT_SIZE: usize;
+
+    fn new() -> Self;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> Vec<u8>;
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+// Hashes whose digest is their whole state, so hashing can be resumed from a
+// published digest. These are the ones open to length extension.
+pub trait Resume: Digest {
+    // the glue padding appended to a message of `len` bytes
+    fn padding(len: usize) -> Vec<u8>;
+    // `len` bytes, padded to a block boundary, were hashed into `digest`
+    fn from_digest(digest: &[u8], len: u64) -> Self;
+}
+
+fn be_words<const N: usize>(digest: &[u8]) -> [u32; N] {
+    std::array::from_fn(|i| u32::from_be_bytes(digest[i * 4..i * 4 + 4].try_into().unwrap()))
+}
+
+fn be_words64<const N: usize>(digest: &[u8]) -> [u64; N] {
+    std::array::from_fn(|i| u64::from_be_bytes(digest[i * 8..i * 8 + 8].try_into().unwrap()))
+}
+
+pub type HashFn = fn(&[u8]) -> Vec<u8>;
+
+// the one-shot hash function called `name`, for the command line
+pub fn by_name(name: &str) -> Option<HashFn> {
+    Some(match name {
+        "md4" => Md4::digest,
+        "md5" => Md5::digest,
+        "sha1" => Sha1::digest,
+        "sha224" => Sha224::digest,
+        "sha256" => Sha256::digest,
+        "sha384" => Sha384::digest,
+        "sha512" => Sha512::digest,
+        "toy16" => ToyHash::<2>::digest,
+        "toy24" => ToyHash::<3>::digest,
+        "toy32" => ToyHash::<4>::digest,
+        _ => return None,
+    })
+}
+
+impl Digest for Sha1 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 20;
+
+    fn new() -> Self {
+        Sha1::new()
+    }
+    fn update(&mut self, data: &[u8]) {
+        Sha1::update(self, data)
+    }
+    fn finalize(self) -> Vec<u8> {
+        Sha1::finalize(self).to_vec()
+    }
+}
+
+impl Digest for Md4 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 16;
+
+    fn new() -> Self {
+        Md4::new()
+    }
+    fn update(&mut self, data: &[u8]) {
+        Md4::update(self, data)
+    }
+    fn finalize(self) -> Vec<u8> {
+        Md4::finalize(self).to_vec()
+    }
+}
+
+impl Digest for Md5 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 16;
+
+    fn new() -> Self {
+        Md5::new()
+    }
+    fn update(&mut self, data: &[u8]) {
+        Md5::update(self, data)
+    }
+    fn finalize(self) -> Vec<u8> {
+        Md5::finalize(self).to_vec()
+    }
+}
+
+impl Digest for Sha224 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 28;
+
+    fn new() -> Self {
+        Sha224::new()
+    }
+    fn update(&mut self, data: &[u8]) {
+        Sha224::update(self, data)
+    }
+    fn finalize(self) -> Vec<u8> {
+        Sha224::finalize(self).to_vec()
+    }
+}
+
+impl Digest for Sha256 {
+    const BLOCK_SIZE: usize = 64;
+    const OUTPUT_SIZE: usize = 32;
+
+    fn new() -> Self {
+        Sha256::new()
+    }
+    fn update(&mut self, data: &[u8]) {
+        Sha256::update(self, data)
+    }
+    fn finalize(self) -> Vec<u8> {
+        Sha256::finalize(self).to_vec()
+    }
+}
+
+impl Digest for Sha384 {
+    const BLOCK_SIZE: usize = 128;
+    const OUTPUT_SIZE: usize = 48;
+
+    fn new() -> Self {
+        Sha384::new()
+    }
+    fn update(&mut self, data: &[u8]) {
+        Sha384::update(self, data)
+    }
+    fn finalize(self) -> Vec<u8> {
+        Sha384::finalize(self).to_vec()
+    }
+}
+
+impl Digest for Sha512 {
+    const BLOCK_SIZE: usize = 128;
+    const OUTPUT_SIZE: usize = 64;
+
+    fn new() -> Self {
+        Sha512::new()
+    }
+    fn update(&mut self, data: &[u8]) {
+        Sha512::update(self, data)
+    }
+    fn finalize(self) -> Vec<u8> {
+        Sha512::finalize(self).to_vec()
+    }
+}
+
+impl<const N: usize> Digest for ToyHash<N> {
+    const BLOCK_SIZE: usize = 16;
+    const OUTPUT_SIZE: usize = N;
+
+    fn new() -> Self {
+        MerkleDamgard::new(AesHash::default())
+    }
+    fn update(&mut self, data: &[u8]) {
+        MerkleDamgard::update(self, data)
+    }
+    fn finalize(self) -> Vec<u8> {
+        MerkleDamgard::finalize(self)
+    }
+}
+
+impl Resume for Sha1 {
+    fn padding(len: usize) -> Vec<u8> {
+        sha1::sha1_padding(len)
+    }
+    fn from_digest(digest: &[u8], len: u64) -> Self {
+        Sha1::with_state(sha1::Variant::SHA1, be_words(digest), len)
+    }
+}
+
+impl Resume for Md4 {
+    fn padding(len: usize) -> Vec<u8> {
+        md4::md4_padding(len)
+    }
+    fn from_digest(digest: &[u8], len: u64) -> Self {
+        Md4::with_state(md4::digest_to_state(digest.try_into().unwrap()), len)
+    }
+}
+
+impl Resume for Md5 {
+    fn padding(len: usize) -> Vec<u8> {
+        md5::md5_padding(len)
+    }
+    fn from_digest(digest: &[u8], len: u64) -> Self {
+        Md5::with_state(md5::digest_to_state(digest.try_into().unwrap()), len)
+    }
+}
+
+impl Resume for Sha256 {
+    fn padding(len: usize) -> Vec<u8> {
+        sha2::sha256_padding(len)
+    }
+    fn from_digest(digest: &[u8], len: u64) -> Self {
+        Sha256::with_state(be_words(digest), len)
+    }
+}
+
+impl Resume for Sha512 {
+    fn padding(len: usize) -> Vec<u8> {
+        sha2::sha512_padding(len)
+    }
+    fn from_digest(digest: &[u8], len: u64) -> Self {
+        Sha512::with_state(be_words64(digest), len)
+    }
+}
+
+impl<const N: usize> Resume for ToyHash<N> {
+    fn padding(len: usize) -> Vec<u8> {
+        ToyHash::<N>::padding(len)
+    }
+    fn from_digest(digest: &[u8], len: u64) -> Self {
+        let c = AesHash::default();
+        let state = c.state_from_bytes(digest);
+        MerkleDamgard::with_state(c, state, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::misc::to_hex;
+
+    use super::*;
+
+    #[test]
+    fn test_by_name() {
+        let cases = [
+            ("md4", "a448017aaf21d8525fc10ae87aa6729d"),
+            ("md5", "900150983cd24fb0d6963f7d28e17f72"),
+            ("sha1", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                "sha256",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+        ];
+        for (name, expect) in cases {
+            assert_eq!(to_hex(&by_name(name).unwrap()(b"abc")), expect);
+        }
+        assert_eq!(by_name("toy24").unwrap()(b"abc").len(), 3);
+        assert!(by_name("md6").is_none());
+    }
+
+    fn check_resume<D: Resume>() {
+        let prefix = b"a message that is hashed first";
+        let digest = D::digest(prefix);
+        assert_eq!(digest.len(), D::OUTPUT_SIZE);
+        let glued = [&prefix[..], &D::padding(prefix.len())].concat();
+        assert!(glued.len().is_multiple_of(D::BLOCK_SIZE));
+
+        let mut hasher = D::from_digest(&digest, glued.len() as u64);
+        hasher.update(b"and extended");
+        assert_eq!(
+            hasher.finalize(),
+            D::digest(&[&glued[..], b"and extended"].concat())
+        );
+    }
+
+    #[test]
+    fn test_resume() {
+        check_resume::<Sha1>();
+        check_resume::<Md4>();
+        check_resume::<Md5>();
+        check_resume::<Sha256>();
+        check_resume::<Sha512>();
+        check_resume::<ToyHash<4>>();
+    }
+}
@@ -0,0 +1,119 @@
+#![allow(unused)]
+use crate::digest::Digest;
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+// RFC 2104: H((K ^ opad) || H((K ^ ipad) || message))
+pub fn hmac<D: Digest>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut key = if key.len() > D::BLOCK_SIZE {
+        let mut hasher = D::new();
+        hasher.update(key);
+        hasher.finalize()
+    } else {
+        key.to_vec()
+    };
+    key.resize(D::BLOCK_SIZE, 0);
+
+    let mut inner = D::new();
+    inner.update(&key.iter().map(|k| k ^ IPAD).collect::<Vec<_>>());
+    inner.update(message);
+    let inner = inner.finalize();
+
+    let mut outer = D::new();
+    outer.update(&key.iter().map(|k| k ^ OPAD).collect::<Vec<_>>());
+    outer.update(&inner);
+    outer.finalize()
+}
+
+pub fn verify_hmac<D: Digest>(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    hmac::<D>(key, message) == mac
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        md4::Md4,
+        misc::to_hex,
+        sha1::Sha1,
+        sha2::{Sha256, Sha512},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha1_rfc2202() {
+        let cases: [(Vec<u8>, &[u8], &str); 7] = [
+            (
+                vec![0x0b; 20],
+                b"Hi There",
+                "b617318655057264e28bc0b6fb378c8ef146be00",
+            ),
+            (
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?",
+                "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            ),
+            (
+                vec![0xaa; 20],
+                &[0xdd; 50],
+                "125d7342b9ac11cd91a39af48aa17b4f63f175d3",
+            ),
+            (
+                (1..=25).collect(),
+                &[0xcd; 50],
+                "4c9007f4026250c6bc8414f9bf50c86c2d7235da",
+            ),
+            (
+                vec![0x0c; 20],
+                b"Test With Truncation",
+                "4c1a03424b55e07fe7f27be1d58bb9324a9a5a04",
+            ),
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "aa4ae5e15272d00e95705637ce8a3b55ed402112",
+            ),
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key and Larger Than One Block-Size Data",
+                "e8e99d0f45237d786d6bbaa7965c7808bbff1a91",
+            ),
+        ];
+        for (key, data, expect) in cases {
+            let mac = hmac::<Sha1>(&key, data);
+            assert_eq!(to_hex(&mac), expect);
+            assert!(verify_hmac::<Sha1>(&key, data, &mac));
+        }
+    }
+
+    #[test]
+    fn test_hmac_sha256_rfc4231() {
+        let mac = hmac::<Sha256>(&[0x0b; 20], b"Hi There");
+        assert_eq!(
+            to_hex(&mac),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        let mac = hmac::<Sha512>(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            to_hex(&mac),
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+        );
+    }
+
+    #[test]
+    fn test_hmac_md4() {
+        let mac = hmac::<Md4>(b"key", b"The quick brown fox jumps over the lazy dog");
+        assert_eq!(mac.len(), 16);
+        assert!(verify_hmac::<Md4>(
+            b"key",
+            b"The quick brown fox jumps over the lazy dog",
+            &mac
+        ));
+        assert!(!verify_hmac::<Md4>(
+            b"kez",
+            b"The quick brown fox jumps over the lazy dog",
+            &mac
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+#![allow(unused)]
+use std::ops::Range;
+
+use crate::digest::{Digest, Resume};
+
+// H(key || message), the MAC length extension breaks
+pub fn secret_prefix_mac<D: Digest>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new();
+    hasher.update(key);
+    hasher.update(message);
+    hasher.finalize()
+}
+
+// forge the mac of `message || glue_padding || extension` assuming the
+// secret key is `key_len` bytes long
+pub fn forge_with_key_len<D: Resume>(
+    key_len: usize,
+    message: &[u8],
+    mac: &[u8],
+    extension: &[u8],
+) -> (Vec<u8>, Vec<u8>) {
+    let glue = D::padding(key_len + message.len());
+    let processed = key_len + message.len() + glue.len();
+    let forged_message = [message, &glue, extension].concat();
+    let mut hasher = D::from_digest(mac, processed as u64);
+    hasher.update(extension);
+    (forged_message, hasher.finalize())
+}
+
+pub fn length_extension<D, F>(
+    message: &[u8],
+    mac: &[u8],
+    extension: &[u8],
+    key_lens: Range<usize>,
+    verify: F,
+) -> Option<(Vec<u8>, Vec<u8>)>
+where
+    D: Resume,
+    F: Fn(&[u8], &[u8]) -> bool,
+{
+    for key_len in key_lens {
+        let (forged_message, forged_mac) =
+            forge_with_key_len::<D>(key_len, message, mac, extension);
+        if verify(&forged_message, &forged_mac) {
+            return Some((forged_message, forged_mac));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        md5::Md5,
+        merkle_damgard::ToyHash,
+        misc::rand_vec,
+        sha2::{Sha256, Sha512},
+    };
+
+    use super::*;
+
+    fn check<D: Resume>() {
+        let key = rand_vec(rand::random::<usize>() % 32 + 1);
+        let message = b"user=guest;expires=never";
+        let mac = secret_prefix_mac::<D>(&key, message);
+        let (forged_message, forged_mac) =
+            length_extension::<D, _>(message, &mac, b";admin=true", 0..64, |m, mac| {
+                secret_prefix_mac::<D>(&key, m) == mac
+            })
+            .expect("cannot forge the mac");
+        assert!(forged_message.ends_with(b";admin=true"));
+        assert_eq!(secret_prefix_mac::<D>(&key, &forged_message), forged_mac);
+    }
+
+    #[test]
+    fn test_length_extension() {
+        check::<Md5>();
+        check::<Sha256>();
+        check::<Sha512>();
+        check::<ToyHash<4>>();
+    }
+}
@@ -4,7 +4,7 @@ use std::{
     io::Read,
 };
 
-use misc::get_eng_histogram;
+use misc::{get_eng_histogram, to_hex};
 use repeating_key_xor::repeating_key_xor_cipher;
 use single_byte_xor_cipher::single_byte_xor_cipher;
 
@@ -12,22 +12,50 @@ use crate::{base64::base64_to_u8, block_cipher_mode::decrypt_in_ecb_mode};
 
 mod aes;
 mod base64;
+mod birthday;
 mod block_cipher_mode;
+mod digest;
+mod hmac;
+mod length_extension;
+mod md4;
+mod md5;
+mod merkle_damgard;
 mod misc;
 mod mt19937;
+mod mt19937_64;
+mod mt19937_gf2;
 mod oracle;
 mod pkcs;
+mod rainbow;
+mod random;
 mod repeating_key_xor;
 mod set_17_cbc_padding_oracle;
 mod set_19_20_ctr_crack;
 mod set_22_mt19937_seed_crack;
 mod set_23_mt19937_crack;
+mod set_24_mt19937_stream_cipher;
+mod set_28_29_sha1_length_extension;
+mod set_30_md4_length_extension;
+mod set_31_32_hmac_timing_leak;
+mod set_52_multicollisions;
+mod set_53_expandable_messages;
+mod set_54_herding;
 mod sha1;
+mod sha2;
 mod single_byte_xor_cipher;
 mod xor;
-mod md4;
 
 fn main() {
+    // `cryptopals hash <name> [file...]` prints the digests of the files, or
+    // of stdin without any
+    let args: Vec<String> = std::env::args().collect();
+    if let [_, cmd, name, files @ ..] = &args[..] {
+        if cmd == "hash" {
+            hash_command(name, files);
+            return;
+        }
+    }
+
     let mut f = File::open("/home/annya/Documents/20k_words.txt").unwrap();
     let mut word_list = "".to_string();
     f.read_to_string(&mut word_list).unwrap();
@@ -39,6 +67,24 @@ fn main() {
     aes_in_ecb_mode_decipher();
 }
 
+fn hash_command(name: &str, files: &[String]) {
+    let Some(hash) = digest::by_name(name) else {
+        eprintln!("unknown hash `{name}`");
+        std::process::exit(1);
+    };
+    if files.is_empty() {
+        let mut data = vec![];
+        std::io::stdin().read_to_end(&mut data).unwrap();
+        println!("{}  -", to_hex(&hash(&data)));
+    }
+    for file in files {
+        match std::fs::read(file) {
+            Ok(data) => println!("{}  {file}", to_hex(&hash(&data))),
+            Err(e) => eprintln!("{file}: {e}"),
+        }
+    }
+}
+
 #[allow(dead_code)]
 fn single_xor_cipher(word_list: HashSet<&str>) {
     let mut f = File::open("data.txt").unwrap();
@@ -2,303 +2,212 @@
 
 use itertools::Itertools;
 use std::num::Wrapping as W;
+use std::ops::Range;
 
-use crate::md4::{attack1_consts::ORDER_REV, g, K1};
+use crate::md4::{g, K1};
 
 use super::{
-    attack1_consts::{ORDER, ROUND1_CMD, ROUND2_CMD, SHIFT1, SHIFT2},
-    f, get_state, get_states_from, h, inv_op, md4, op,
+    attack1_consts::{ORDER, ROUND3_ORDER, SHIFT1, SHIFT2, SHIFT3},
+    compress,
+    conditions::{Condition, Conditions, Value},
+    f, h, inv_op, md4, op,
     state::{State, StateType},
-    Wu32, K2, S0,
+    to_block, Md4, Wu32, K2, S0,
 };
+use crate::random::{os, RandomSource};
 
-#[derive(Debug)]
-pub enum CmdType {
-    Equal(Option<StateType>),
-    Unset,
-    Set,
+fn correct_bit_set(u: Wu32, i: u32) -> Wu32 {
+    u | W(1 << i)
 }
-
-pub struct Cmd {
-    pub typ: CmdType,
-    pub bit: u32,
+fn correct_bit_unset(u: Wu32, i: u32) -> Wu32 {
+    u & W(!(1 << i))
 }
-impl Cmd {
-    pub fn debug(&self, v: &str, u: &str, round: usize) {
-        print!("\tperforming round {round} command: ");
-        let bit = self.bit + 1;
-        match self.typ {
-            CmdType::Equal(_) => print!("{v}_{} = {u}_{}", bit, bit),
-            CmdType::Unset => print!("{v}_{} = 0", bit),
-            CmdType::Set => print!("{v}_{} = 1", bit),
+
+// The states of one block in the order they are computed: `q[0..4]` holds
+// a0, d0, c0, b0 and `q[i + 4]` is the output of step `i`, so q[n] is the
+// state with `State::index() == n`.
+type History = [Wu32; 52];
+
+// how often a round 2 correction is tried before a4 is drawn again
+const TRIES: usize = 1024;
+
+fn msg_index(i: usize) -> usize {
+    match i {
+        0..=15 => i,
+        16..=31 => {
+            let j = i - 16;
+            (j % 4) * 4 + j / 4
         }
-        println!();
+        _ => ROUND3_ORDER[i - 32],
+    }
+}
+
+fn step(q: &History, msg: &[Wu32; 16], i: usize) -> Wu32 {
+    let (a, d, c, b) = (q[i], q[i + 1], q[i + 2], q[i + 3]);
+    let m = msg[msg_index(i)];
+    match i {
+        0..=15 => op(f, a, b, c, d, m, SHIFT1[i % 4]),
+        16..=31 => op(g, a, b, c, d, m + K1, SHIFT2[i % 4]),
+        _ => op(h, a, b, c, d, m + K2, SHIFT3[i % 4]),
     }
 }
 
-fn create_weak_message(msg: &[Wu32; 16]) -> Vec<Wu32> {
-    let mut x = Vec::from(msg);
-    x[1] += W(1 << 31);
-    x[2] = x[2] + W(1 << 31) - W(1 << 28);
-    x[12] -= W(1 << 16);
-    x
+// the message word that makes step `i` (0..32) output `v`
+fn solve(q: &History, i: usize, v: Wu32) -> Wu32 {
+    let (a, d, c, b) = (q[i], q[i + 1], q[i + 2], q[i + 3]);
+    if i < 16 {
+        inv_op(f, a, b, c, d, W(0), SHIFT1[i % 4], v)
+    } else {
+        inv_op(g, a, b, c, d, K1, SHIFT2[i % 4], v)
+    }
 }
 
-fn correct_bit_equal(u: Wu32, v: Wu32, i: u32) -> Wu32 {
-    u ^ ((u ^ v) & W(1 << i))
+// `iv` is the chaining value a0, b0, c0, d0 the block is compressed from
+fn new_history(iv: &[Wu32; 4]) -> History {
+    let mut q = [W(0); 52];
+    for (n, t) in ORDER.iter().enumerate() {
+        q[n] = iv[*t as usize];
+    }
+    q
 }
-fn correct_bit_set(u: Wu32, i: u32) -> Wu32 {
-    u | W(1 << i)
+
+fn correct(q: &History, mut v: Wu32, conds: &[Condition]) -> Wu32 {
+    for cond in conds {
+        v = if cond.expected(q) {
+            correct_bit_set(v, cond.bit)
+        } else {
+            correct_bit_unset(v, cond.bit)
+        };
+    }
+    v
 }
-fn correct_bit_unset(u: Wu32, i: u32) -> Wu32 {
-    u & W(!(1 << i))
+
+fn satisfied(q: &History, v: Wu32, cond: &Condition) -> bool {
+    (v.0 >> cond.bit & 1 == 1) == cond.expected(q)
 }
 
-fn check(msg: &[Wu32; 16]) {
-    let mut state = [State::default(); 4];
-    for (i, iv) in S0.iter().enumerate() {
-        state[i] = State {
-            typ: i.into(),
-            num: 0,
-            val: iv.clone(),
+// whether `msg` satisfies all the conditions of the first `steps` steps
+fn check(iv: &[Wu32; 4], msg: &[Wu32; 16], conds: &Conditions, steps: usize) -> bool {
+    let mut q = new_history(iv);
+    for i in 0..steps {
+        let v = step(&q, msg, i);
+        if !conds.step(i).iter().all(|c| satisfied(&q, v, c)) {
+            return false;
         }
+        q[i + 4] = v;
     }
-    let mut vals = state.iter().map(|v| v.val.clone()).collect::<Vec<_>>();
-    for i in 0..16 {
-        let idx = ORDER[i % 4];
-        let idx_num = idx as usize;
-        let v = op(
-            f,
-            vals[idx_num],
-            vals[(idx_num + 1) % 4],
-            vals[(idx_num + 2) % 4],
-            vals[(idx_num + 3) % 4],
-            msg[i],
-            SHIFT1[i % 4],
-        );
-        vals[idx_num] = v;
-        let round = i / 4 + 1;
-        let cmds = ROUND1_CMD[i].iter();
-        for cmd in cmds {
-            match cmd.typ {
-                CmdType::Equal(_) => {
-                    assert!(
-                        (v ^ vals[(idx_num + 1) % 4]).0 & (1 << cmd.bit) == 0,
-                        "cmd {:?} failed, i: {}",
-                        cmd.typ,
-                        i
-                    )
-                }
-                CmdType::Unset => {
-                    assert!(
-                        v.0 & (1 << cmd.bit) == 0,
-                        "cmd {:?} failed, i: {}",
-                        cmd.typ,
-                        i
-                    )
-                }
-                CmdType::Set => {
-                    assert!(
-                        v.0 & (1 << cmd.bit) != 0,
-                        "cmd {:?} failed, i: {}",
-                        cmd.typ,
-                        i
-                    )
-                }
-            }
+    true
+}
+
+// every condition along with whether `msg` satisfies it
+pub fn report(iv: &[Wu32; 4], msg: &[Wu32; 16], conds: &Conditions) -> Vec<(Condition, bool)> {
+    let mut q = new_history(iv);
+    let mut res = vec![];
+    for i in 0..conds.len() {
+        let v = step(&q, msg, i);
+        for cond in conds.step(i) {
+            res.push((*cond, satisfied(&q, v, cond)));
         }
+        q[i + 4] = v;
     }
-    // check round2
-    for (i, cmds) in ROUND2_CMD.iter().enumerate() {
-        let idx_remain = i % 4;
-        let idx = ORDER[idx_remain];
-        let idx_num = idx as usize;
-        let v = op(
-            g,
-            vals[idx_num],
-            vals[(idx_num + 1) % 4],
-            vals[(idx_num + 2) % 4],
-            vals[(idx_num + 3) % 4],
-            msg[idx_remain * 4 + i / 4] + K1,
-            SHIFT2[i % 4],
-        );
-        vals[idx_num] = v;
-        let round = i / 4 + 5;
-        for cmd in *cmds {
-            let prev_ind = {
-                match cmd.typ {
-                    CmdType::Equal(prev) => {
-                        if let Some(prev) = prev {
-                            prev as usize
-                        } else {
-                            (idx_num + 1) % 4
-                        }
-                    }
-                    _ => (idx_num + 1) % 4,
-                }
-            };
-            let prev_state = format!(
-                "{:?}{}",
-                ORDER_REV[prev_ind],
-                round - if idx == StateType::A { 1 } else { 0 }
-            );
-            // cmd.debug(&cur_state, &prev_state, 2);
-            match cmd.typ {
-                CmdType::Equal(_) => {
-                    assert!(
-                        (v ^ vals[prev_ind]).0 & (1 << cmd.bit) == 0,
-                        "cmd {:?} failed, i: {}",
-                        cmd.typ,
-                        i
-                    )
-                }
-                CmdType::Unset => {
-                    assert!(
-                        v.0 & (1 << cmd.bit) == 0,
-                        "cmd {:?} failed, i: {}",
-                        cmd.typ,
-                        i
-                    )
-                }
-                CmdType::Set => {
-                    assert!(
-                        v.0 & (1 << cmd.bit) != 0,
-                        "cmd {:?} failed, i: {}",
-                        cmd.typ,
-                        i
-                    )
-                }
-            }
+    res
+}
+
+// the bits of state `n` that no condition mentions, changing them keeps
+// every condition on the state and on the states referring to it
+fn free_bits(conds: &Conditions, n: usize) -> Wu32 {
+    let mut mask = u32::MAX;
+    for cond in conds.iter() {
+        let other = match cond.value {
+            Value::Equal(s) | Value::NotEqual(s) => Some(s.index()),
+            _ => None,
+        };
+        if cond.state.index() == n || other == Some(n) {
+            mask &= !(1 << cond.bit);
         }
     }
+    W(mask)
+}
+
+// Replace the round 1 state of step `k` and solve its message word and the
+// next four again, so that the following round 1 states stay the same. The
+// round 2 states have to be computed again.
+fn set_state(q: &mut History, msg: &mut [Wu32; 16], k: usize, v: Wu32) {
+    q[k + 4] = v;
+    for j in k..(k + 5).min(16) {
+        msg[j] = solve(q, j, q[j + 4]);
+    }
 }
 
-fn attack_round1(
-    state: &mut [State; 4],
-    idx: StateType,
-    msg_idx: usize,
+// Multi-message modification of round 2 step `i` through the round 1 step `k`
+// computing the same message word. The corrected output of step `i` is tried
+// first, when the round 1 state it needs changes a conditioned bit, the free
+// bits of that state are drawn at random instead.
+fn correct_round2(
+    rng: &mut impl RandomSource,
+    q: &mut History,
     msg: &mut [Wu32; 16],
-    shift: u32,
-    cmds: &[Cmd],
-) {
-    let vals = state.iter().map(|v| v.val.clone()).collect::<Vec<_>>();
-    let st = state
-        .iter_mut()
-        .find(|s| s.typ == idx)
-        .expect("cannot find state");
-    (*st).num += 1;
-    let idx_num = idx as usize;
-    let mut v = op(
-        f,
-        vals[idx_num],
-        vals[(idx_num + 1) % 4],
-        vals[(idx_num + 2) % 4],
-        vals[(idx_num + 3) % 4],
-        msg[msg_idx],
-        shift,
-    );
-    let round = (*st).num;
-    let cur_state = format!("{:?}{}", st.typ, round);
-    for cmd in cmds {
-        let bit = cmd.bit;
-        v = match cmd.typ {
-            CmdType::Equal(_) => correct_bit_equal(v, vals[(idx_num + 1) % 4], bit),
-            CmdType::Unset => correct_bit_unset(v, bit),
-            CmdType::Set => correct_bit_set(v, bit),
+    conds: &Conditions,
+    i: usize,
+    k: usize,
+) -> bool {
+    let free = free_bits(conds, k + 4);
+    for _ in 0..TRIES {
+        let v = step(q, msg, i);
+        if conds.step(i).iter().all(|c| satisfied(q, v, c)) {
+            q[i + 4] = v;
+            return true;
+        }
+        let mut wanted = *msg;
+        wanted[k] = solve(q, i, correct(q, v, conds.step(i)));
+        let state = step(q, &wanted, k);
+        let state = if (state ^ q[k + 4]) & !free == W(0) {
+            state
+        } else {
+            q[k + 4] ^ (W(rng.next_u32()) & free)
         };
+        set_state(q, msg, k, state);
     }
-
-    let old_msg = msg[msg_idx];
-    msg[msg_idx] = inv_op(
-        f,
-        vals[idx_num],
-        vals[(idx_num + 1) % 4],
-        vals[(idx_num + 2) % 4],
-        vals[(idx_num + 3) % 4],
-        W(0),
-        shift,
-        v,
-    );
-
-    (*st).val = v;
+    false
 }
 
-fn attack_round2(
-    state: &mut [State; 4],
-    idx: StateType,
-    msg_idx: usize,
+// Draw the free bits of the round 1 steps `ks` again until the round 2 steps
+// `steps` hold. The steps before them must not use the words of `ks`.
+fn redraw_round2(
+    rng: &mut impl RandomSource,
+    q: &mut History,
     msg: &mut [Wu32; 16],
-    shift: u32,
-    cmds: &[Cmd],
-    mut affecting_state: State,
-) {
-    let vals = state.iter().map(|v| v.val.clone()).collect::<Vec<_>>();
-    let st = state
-        .iter_mut()
-        .find(|s| s.typ == idx)
-        .expect("cannot find state");
-    (*st).num += 1;
-    let idx_num = idx as usize;
-    let mut v = op(
-        g,
-        vals[idx_num],
-        vals[(idx_num + 1) % 4],
-        vals[(idx_num + 2) % 4],
-        vals[(idx_num + 3) % 4],
-        msg[msg_idx] + K1,
-        shift,
-    );
-    let round = (*st).num;
-    for cmd in cmds {
-        let bit = cmd.bit;
-        v = match cmd.typ {
-            CmdType::Equal(v_id) => {
-                let state_id = v_id.unwrap() as usize;
-                correct_bit_equal(v, vals[state_id], bit)
-            }
-            CmdType::Unset => correct_bit_unset(v, bit),
-            CmdType::Set => correct_bit_set(v, bit),
-        };
+    conds: &Conditions,
+    ks: Range<usize>,
+    steps: Range<usize>,
+) -> bool {
+    let free = ks.clone().map(|k| free_bits(conds, k + 4)).collect_vec();
+    for _ in 0..TRIES {
+        if run(q, msg, conds, steps.clone()) {
+            return true;
+        }
+        for (k, free) in ks.clone().zip(&free) {
+            set_state(q, msg, k, q[k + 4] ^ (W(rng.next_u32()) & *free));
+        }
     }
+    false
+}
 
-    let mut old_states = get_states_from(affecting_state.prev_n(3), 8, msg);
-    let old_msg = msg[msg_idx];
-    msg[msg_idx] = inv_op(
-        g,
-        vals[idx_num],
-        vals[(idx_num + 1) % 4],
-        vals[(idx_num + 2) % 4],
-        vals[(idx_num + 3) % 4],
-        K1,
-        shift,
-        v,
-    );
-
-    (*st).val = v;
-
-    // multi-step correction
-    get_state(&mut affecting_state, msg);
-
-    old_states[3] = affecting_state;
-    let ind = affecting_state.typ as usize;
-
-    for i in 0..4 {
-        msg[msg_idx + 1 + i] = inv_op(
-            f,
-            old_states[i].val,
-            old_states[3 + i].val,
-            old_states[2 + i].val,
-            old_states[1 + i].val,
-            W(0),
-            SHIFT1[(i + 1 + ind) % 4],
-            old_states[4 + i].val,
-        );
+// compute the states of `steps` into `q`, stopping at the first one that
+// misses a condition
+fn run(q: &mut History, msg: &[Wu32; 16], conds: &Conditions, steps: Range<usize>) -> bool {
+    for i in steps {
+        let v = step(q, msg, i);
+        if !conds.step(i).iter().all(|c| satisfied(q, v, c)) {
+            return false;
+        }
+        q[i + 4] = v;
     }
-    let new_states = get_states_from(affecting_state.prev_n(3), 8, msg);
+    true
 }
 
 fn create_colliding_msg(msg: [Wu32; 16]) -> ([Wu32; 16], [Wu32; 16]) {
-    let mut n_msg = msg.clone();
+    let mut n_msg = msg;
     n_msg[1] = msg[1] + W(1 << 31);
     n_msg[2] = msg[2] + W((1 << 31) - (1 << 28));
     n_msg[12] = msg[12] - W(1 << 16);
@@ -312,101 +221,215 @@ fn convert_msg(msg: [Wu32; 16]) -> Vec<u8> {
         .concat()
 }
 
-fn format_msg(msg: [Wu32; 16]) -> String {
-    convert_msg(msg)
-        .iter()
-        .fold("".to_string(), |acc, v| format!("{acc}{v:0>2x}"))
+// Search two different blocks with the same MD4 digest using Wang's
+// conditions. Messages satisfying the conditions of rounds 1 and 2 are
+// generated until the remaining ones happen to hold as well.
+pub fn attack() -> (Vec<u8>, Vec<u8>) {
+    attack_from(&S0)
 }
 
-fn attack() {
+// the same search for a block compressed from any chaining value
+pub fn attack_from(iv: &[Wu32; 4]) -> (Vec<u8>, Vec<u8>) {
+    attack_with(iv, &Conditions::wang())
+}
+
+pub fn attack_with(iv: &[Wu32; 4], conds: &Conditions) -> (Vec<u8>, Vec<u8>) {
+    attack_with_rng(&mut os(), iv, conds)
+}
+
+pub fn attack_with_rng(
+    rng: &mut impl RandomSource,
+    iv: &[Wu32; 4],
+    conds: &Conditions,
+) -> (Vec<u8>, Vec<u8>) {
     loop {
-        let msg = generate_msg();
-        // maybe this msg is not fully satisfy the conditions a1~d4, but the
-        // conditions in Wang's paper is not necessary, it is still good to
-        // have a try
+        let msg = generate_msg(rng, iv, conds);
+        if !check(iv, &msg, conds, conds.len()) {
+            continue;
+        }
         let (msg, n_msg) = create_colliding_msg(msg);
-        let (md4_1, md4_2) = (md4(&convert_msg(msg)), md4(&convert_msg(n_msg)));
-        if md4_1 == md4_2 {
-            let (msg, n_msg) = (format_msg(msg), format_msg(n_msg));
-            println!("we found it!\nM1: {}\nM2: {}\nmd4: {}", msg, n_msg, md4_1);
-            break;
+        let (mut s1, mut s2) = (*iv, *iv);
+        compress(&mut s1, &msg);
+        compress(&mut s2, &n_msg);
+        if s1 == s2 {
+            return (convert_msg(msg), convert_msg(n_msg));
         }
     }
 }
 
-fn generate_msg() -> [Wu32; 16] {
-    let mut m = [W(u32::MAX); 16];
+// Two messages starting with `prefix` that have the same MD4 digest. The
+// prefix is padded with zeros to a block boundary and the colliding blocks
+// are searched from the state reached after it. Anything appended to both
+// messages keeps the digests equal.
+pub fn collide_with_prefix(prefix: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut padded = prefix.to_vec();
+    padded.resize(prefix.len().div_ceil(64) * 64, 0);
+    let mut hasher = Md4::new();
+    hasher.update(&padded);
+    let (b1, b2) = attack_from(&hasher.state());
+    ([padded.clone(), b1].concat(), [padded, b2].concat())
+}
+
+fn generate_msg(rng: &mut impl RandomSource, iv: &[Wu32; 4], conds: &Conditions) -> [Wu32; 16] {
+    let mut m = [W(0); 16];
     for v in m.iter_mut() {
-        *v = W(rand::random());
-    }
-    let mut state = [State::default(); 4];
-    for (i, iv) in S0.iter().enumerate() {
-        state[i] = State {
-            typ: i.into(),
-            num: 0,
-            val: iv.clone(),
-        }
+        *v = W(rng.next_u32());
     }
+    let mut q = new_history(iv);
+    // single-message modification: every round 1 state is corrected and the
+    // message word is solved from it
     for i in 0..16 {
-        attack_round1(
-            &mut state,
-            ORDER[i % 4],
-            i,
-            &mut m,
-            SHIFT1[i % 4],
-            ROUND1_CMD[i],
-        );
+        let v = correct(&q, step(&q, &m, i), conds.step(i));
+        m[i] = solve(&q, i, v);
+        q[i + 4] = v;
     }
-    let msg_idx_arr = [0, 4, 8, 12];
-    let affecting_states = [
-        State {
-            typ: StateType::A,
-            num: 1,
-            val: W(0),
-        },
-        State {
-            typ: StateType::A,
-            num: 2,
-            val: W(0),
-        },
-        State {
-            typ: StateType::A,
-            num: 3,
-            val: W(0),
-        },
-        State {
-            typ: StateType::A,
-            num: 4,
-            val: W(0),
-        },
-    ];
-    for (i, cmds) in ROUND2_CMD.iter().enumerate() {
-        attack_round2(
-            &mut state,
-            ORDER[i % 4],
-            i / 4 + msg_idx_arr[i % 4],
-            &mut m,
-            SHIFT2[i % 4],
-            cmds,
-            affecting_states[i],
-        );
+    // Round 2 uses the words in the order 0, 4, 8, 12, 1, 5, 9, ... and a
+    // round 1 state changes its own word and the next four, so a5 and d5 are
+    // corrected through a1 and a2, c5 through d2..a3 and b5..c6 through
+    // d3..b3 without undoing the states before. When one of them gets stuck
+    // a4, which feeds a5 directly, is drawn again and everything is redone.
+    let a4_free = free_bits(conds, 16);
+    loop {
+        let holds = correct_round2(rng, &mut q, &mut m, conds, 16, 0)
+            && correct_round2(rng, &mut q, &mut m, conds, 17, 4)
+            && redraw_round2(rng, &mut q, &mut m, conds, 5..9, 18..19)
+            && redraw_round2(rng, &mut q, &mut m, conds, 9..12, 19..32);
+        if holds {
+            return m;
+        }
+        let a4 = q[16] ^ (W(rng.next_u32()) & a4_free);
+        set_state(&mut q, &mut m, 12, a4);
     }
-    m
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::random::seeded;
+
     use super::*;
 
     #[test]
     fn test_md4_attack() {
-        attack();
-        panic!()
+        let (m1, m2) = attack();
+        assert_ne!(m1, m2);
+        assert_eq!(md4(&m1), md4(&m2));
+    }
+
+    #[test]
+    fn test_attack_with_rng() {
+        // the same seed finds the same pair
+        let conds = Conditions::wang();
+        let pair = attack_with_rng(&mut seeded(4), &S0, &conds);
+        assert_eq!(attack_with_rng(&mut seeded(4), &S0, &conds), pair);
+        assert_eq!(md4(&pair.0), md4(&pair.1));
+    }
+
+    #[test]
+    fn test_attack_from() {
+        let iv = [W(0x01234567), W(0x89abcdef), W(0xfedcba98), W(0x76543210)];
+        let (m1, m2) = attack_from(&iv);
+        assert_ne!(m1, m2);
+        let (mut s1, mut s2) = (iv, iv);
+        compress(&mut s1, &to_block(&m1));
+        compress(&mut s2, &to_block(&m2));
+        assert_eq!(s1, s2);
+    }
+
+    #[test]
+    fn test_collide_with_prefix() {
+        let prefix = b"chosen prefix that doesn't end on a block boundary";
+        let (m1, m2) = collide_with_prefix(prefix);
+        assert_ne!(m1, m2);
+        assert_eq!(m1.len(), 128);
+        assert!(m1.starts_with(prefix) && m2.starts_with(prefix));
+        assert_eq!(md4(&m1), md4(&m2));
+        // the collision survives a common suffix
+        let suffix = b"and a suffix";
+        assert_eq!(
+            md4(&[m1, suffix.to_vec()].concat()),
+            md4(&[m2, suffix.to_vec()].concat())
+        );
+    }
+
+    #[test]
+    fn test_step() {
+        let states: [u32; 20] = [
+            0x67452301, 0x10325476, 0x98badcfe, 0xefcdab89, 0xffffffb7, 0x1f80, 0x44e430c4,
+            0x59dd534c, 0x420c7d2, 0x626141a0, 0x4104af5, 0xc742bff0, 0x4260f609, 0x5b3079d4,
+            0x3801a653, 0x2310d19, 0x691356ec, 0x950ef735, 0x8a67f1d9, 0x966b1a40,
+        ];
+        let msg = [
+            W(0xfffffff7),
+            W(0x0),
+            W(0x8fffffff),
+            W(0xff7fffff),
+            W(0xffbffc7f),
+            W(0x2fbf),
+            W(0xfffffd79),
+            W(0xfdfffffa),
+            W(0xa0bff),
+            W(0x4605f),
+            W(0xfff3bf1f),
+            W(0xffffeffe),
+            W(0xf1bfffff),
+            W(0xffe7ffff),
+            W(0xfffc487f),
+            W(0x80000e7f),
+        ];
+        // the round 1 states of `msg` in the order they are computed
+        let mut q = new_history(&S0);
+        for i in 0..16 {
+            q[i + 4] = step(&q, &msg, i);
+        }
+        assert_eq!(q[..20].iter().map(|v| v.0).collect_vec(), states);
     }
 
     #[test]
     fn test_round1() {
-        let msg = generate_msg();
-        check(&msg);
+        let mut rng = seeded(1);
+        let conds = Conditions::wang();
+        for _ in 0..100 {
+            let msg = generate_msg(&mut rng, &S0, &conds);
+            assert!(check(&S0, &msg, &conds, 16));
+        }
+    }
+
+    #[test]
+    fn test_round2_modification() {
+        let conds = Conditions::wang();
+        // a1,7 is set, d1,8 and d1,11 copy a1
+        assert_eq!(free_bits(&conds, 4), W(!(1 << 6 | 1 << 7 | 1 << 10)));
+
+        // every condition of rounds 1 and 2 holds, round 3 is left to chance
+        let mut rng = seeded(2);
+        for _ in 0..20 {
+            let msg = generate_msg(&mut rng, &S0, &conds);
+            assert!(check(&S0, &msg, &conds, 32));
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let mut rng = seeded(3);
+        let conds = Conditions::wang();
+        let msg = generate_msg(&mut rng, &S0, &conds);
+        let res = report(&S0, &msg, &conds);
+        assert_eq!(res.len(), conds.iter().count());
+        assert!(res.iter().filter(|(c, _)| c.step() < 16).all(|(_, ok)| *ok));
+
+        // another path only has to satisfy its own conditions
+        let conds = Conditions::parse("a1,7 = b0,7\nd1,32 = a1,32 + 1\nc1,1 = 1").unwrap();
+        let msg = generate_msg(&mut rng, &S0, &conds);
+        assert_eq!(
+            report(&S0, &msg, &conds)
+                .iter()
+                .map(|(c, ok)| (c.to_string(), *ok))
+                .collect_vec(),
+            [
+                ("a1,7 = b0,7".to_string(), true),
+                ("d1,32 = a1,32 + 1".to_string(), true),
+                ("c1,1 = 1".to_string(), true),
+            ]
+        );
     }
 }
@@ -0,0 +1,341 @@
+#![allow(unused)]
+use std::fmt;
+
+use super::state::{State, StateType};
+use core::num::Wrapping as W;
+
+// The sufficient conditions of Wang et al. for the MD4 collision, one per
+// line in the notation of the paper: `a1,7 = b0,7` means that bit 7 of a1
+// equals bit 7 of b0, bits are counted from 1. `x,i + 1` is the flipped bit.
+pub const WANG_CONDITIONS: &str = "
+# round 1
+a1,7 = b0,7
+d1,7 = 0
+d1,8 = a1,8
+d1,11 = a1,11
+c1,7 = 1
+c1,8 = 1
+c1,11 = 0
+c1,26 = d1,26
+b1,7 = 1
+b1,8 = 0
+b1,11 = 0
+b1,26 = 0
+a2,8 = 1
+a2,11 = 1
+a2,26 = 0
+a2,14 = b1,14
+d2,14 = 0
+d2,19 = a2,19
+d2,20 = a2,20
+d2,21 = a2,21
+d2,22 = a2,22
+d2,26 = 1
+c2,13 = d2,13
+c2,14 = 0
+c2,15 = d2,15
+c2,19 = 0
+c2,20 = 0
+c2,21 = 1
+c2,22 = 0
+b2,13 = 1
+b2,14 = 1
+b2,15 = 0
+b2,17 = c2,17
+b2,19 = 0
+b2,20 = 0
+b2,21 = 0
+b2,22 = 0
+a3,13 = 1
+a3,14 = 1
+a3,15 = 1
+a3,17 = 0
+a3,19 = 0
+a3,20 = 0
+a3,21 = 0
+a3,23 = b2,23
+a3,22 = 1
+a3,26 = b2,26
+d3,13 = 1
+d3,14 = 1
+d3,15 = 1
+d3,17 = 0
+d3,20 = 0
+d3,21 = 1
+d3,22 = 1
+d3,23 = 0
+d3,26 = 1
+d3,30 = a3,30
+c3,17 = 1
+c3,20 = 0
+c3,21 = 0
+c3,22 = 0
+c3,23 = 0
+c3,26 = 0
+c3,30 = 1
+c3,32 = d3,32
+b3,20 = 0
+b3,21 = 1
+b3,22 = 1
+b3,23 = c3,23
+b3,26 = 1
+b3,30 = 0
+b3,32 = 0
+a4,23 = 0
+a4,26 = 0
+a4,27 = b3,27
+a4,29 = b3,29
+a4,30 = 1
+a4,32 = 0
+d4,23 = 0
+d4,26 = 0
+d4,27 = 1
+d4,29 = 1
+d4,30 = 0
+d4,32 = 1
+c4,19 = d4,19
+c4,23 = 1
+c4,26 = 1
+c4,27 = 0
+c4,29 = 0
+c4,30 = 0
+b4,19 = 0
+b4,26 = c4,26
+b4,27 = 1
+b4,29 = 1
+b4,30 = 0
+b4,32 = c4,32
+
+# round 2
+a5,19 = c4,19
+a5,26 = 1
+a5,27 = 0
+a5,29 = 1
+a5,32 = 1
+d5,19 = a5,19
+d5,26 = b4,26
+d5,27 = b4,27
+d5,29 = b4,29
+d5,32 = b4,32
+c5,26 = d5,26
+c5,27 = d5,27
+c5,29 = d5,29
+c5,30 = d5,30
+c5,32 = d5,32
+b5,29 = c5,29
+b5,30 = 1
+b5,32 = 0
+a6,29 = 1
+a6,32 = 1
+d6,29 = b5,29
+c6,29 = d6,29
+c6,30 = d6,30 + 1
+c6,32 = d6,32 + 1
+
+# round 3
+b9,32 = 1
+a10,32 = 1
+";
+
+// the 48 steps of one block compute a1..b12
+const LAST_NUM: u8 = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Zero,
+    One,
+    // the same bit of an earlier state
+    Equal(State),
+    // the flipped bit of an earlier state
+    NotEqual(State),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Condition {
+    pub state: State,
+    // counted from 0
+    pub bit: u32,
+    pub value: Value,
+}
+
+impl Condition {
+    // the step (0..48) computing the state of the condition
+    pub fn step(&self) -> usize {
+        self.state.index() - 4
+    }
+    // the bit the state should have, given the values of the earlier states
+    // indexed like `State::index`
+    pub fn expected(&self, states: &[W<u32>]) -> bool {
+        let bit = |s: State| states[s.index()].0 >> self.bit & 1 == 1;
+        match self.value {
+            Value::Zero => false,
+            Value::One => true,
+            Value::Equal(s) => bit(s),
+            Value::NotEqual(s) => !bit(s),
+        }
+    }
+}
+
+fn state_name(s: &State) -> String {
+    format!("{}{}", format!("{:?}", s.typ).to_lowercase(), s.num)
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bit = self.bit + 1;
+        write!(f, "{},{bit} = ", state_name(&self.state))?;
+        match self.value {
+            Value::Zero => write!(f, "0"),
+            Value::One => write!(f, "1"),
+            Value::Equal(s) => write!(f, "{},{bit}", state_name(&s)),
+            Value::NotEqual(s) => write!(f, "{},{bit} + 1", state_name(&s)),
+        }
+    }
+}
+
+// `d1,8` -> (d1, 7)
+fn parse_bit(s: &str) -> Result<(State, u32), String> {
+    let (state, bit) = s
+        .split_once(',')
+        .ok_or(format!("expected `state,bit`, got `{s}`"))?;
+    let mut chars = state.chars();
+    let typ = match chars.next() {
+        Some('a') => StateType::A,
+        Some('b') => StateType::B,
+        Some('c') => StateType::C,
+        Some('d') => StateType::D,
+        _ => return Err(format!("unknown state `{state}`")),
+    };
+    let num: u8 = chars
+        .as_str()
+        .parse()
+        .map_err(|_| format!("unknown state `{state}`"))?;
+    if num > LAST_NUM {
+        return Err(format!("state `{state}` is out of range"));
+    }
+    let bit: u32 = bit
+        .trim()
+        .parse()
+        .map_err(|_| format!("bad bit in `{s}`"))?;
+    if !(1..=32).contains(&bit) {
+        return Err(format!("bit {bit} is out of range"));
+    }
+    Ok((
+        State {
+            typ,
+            num,
+            val: W(0),
+        },
+        bit - 1,
+    ))
+}
+
+pub fn parse_condition(line: &str) -> Result<Condition, String> {
+    let (lhs, rhs) = line
+        .split_once('=')
+        .ok_or(format!("expected `=` in `{line}`"))?;
+    let (state, bit) = parse_bit(lhs.trim())?;
+    if state.num == 0 {
+        return Err(format!("`{}` is part of the chaining value", lhs.trim()));
+    }
+    let value = match rhs.trim() {
+        "0" => Value::Zero,
+        "1" => Value::One,
+        rhs => {
+            let (rhs, flipped) = match rhs.strip_suffix("+ 1") {
+                Some(rhs) => (rhs.trim(), true),
+                None => (rhs, false),
+            };
+            let (other, other_bit) = parse_bit(rhs)?;
+            if other_bit != bit {
+                return Err(format!("`{line}` compares different bits"));
+            }
+            // the other state must be known when the condition is checked
+            if other.index() >= state.index() {
+                return Err(format!("`{rhs}` isn't computed before `{}`", lhs.trim()));
+            }
+            if flipped {
+                Value::NotEqual(other)
+            } else {
+                Value::Equal(other)
+            }
+        }
+    };
+    Ok(Condition { state, bit, value })
+}
+
+// conditions grouped by the step computing their state
+pub struct Conditions {
+    steps: Vec<Vec<Condition>>,
+}
+
+impl Conditions {
+    // one condition per line, `#` starts a comment
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut steps = vec![vec![]; 48];
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let cond = parse_condition(line).map_err(|e| format!("line {}: {e}", n + 1))?;
+            steps[cond.step()].push(cond);
+        }
+        Ok(Self { steps })
+    }
+    pub fn wang() -> Self {
+        Self::parse(WANG_CONDITIONS).unwrap()
+    }
+    pub fn step(&self, i: usize) -> &[Condition] {
+        &self.steps[i]
+    }
+    // the number of steps up to the last one with a condition
+    pub fn len(&self) -> usize {
+        self.steps
+            .iter()
+            .rposition(|s| !s.is_empty())
+            .map_or(0, |i| i + 1)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Condition> {
+        self.steps.iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_condition() {
+        let cond = parse_condition("d1,8 = a1,8").unwrap();
+        assert_eq!(cond.state.typ, StateType::D);
+        assert_eq!(cond.state.num, 1);
+        assert_eq!(cond.bit, 7);
+        assert_eq!(cond.step(), 1);
+        assert!(matches!(cond.value, Value::Equal(s) if s.typ == StateType::A && s.num == 1));
+
+        for line in ["d1,7 = 0", "a5,32 = 1", "c6,30 = d6,30 + 1", "a1,7 = b0,7"] {
+            assert_eq!(parse_condition(line).unwrap().to_string(), line);
+        }
+
+        // the other state must come first and use the same bit
+        assert!(parse_condition("a1,8 = d1,8").is_err());
+        assert!(parse_condition("d1,8 = d1,8").is_err());
+        assert!(parse_condition("d1,8 = a1,9").is_err());
+        assert!(parse_condition("b0,1 = 1").is_err());
+        assert!(parse_condition("a1,33 = 1").is_err());
+        assert!(parse_condition("e1,3 = 1").is_err());
+        assert!(parse_condition("a1,3 = 2").is_err());
+    }
+
+    #[test]
+    fn test_wang_conditions() {
+        let conditions = Conditions::wang();
+        assert_eq!(conditions.iter().count(), 122);
+        // a10 is computed in step 36
+        assert_eq!(conditions.len(), 37);
+        assert_eq!(conditions.step(0).len(), 1);
+        assert!(conditions.step(31).is_empty());
+        assert!(Conditions::parse("a1,7 = b0,7\nd1,1 = x").is_err());
+    }
+}
@@ -1,10 +1,11 @@
 mod attack1;
 mod attack1_consts;
+mod conditions;
 mod state;
 use core::num::Wrapping as W;
 
-use attack1_consts::{ORDER, SHIFT1};
-use state::{State, StateType};
+use crate::merkle_damgard::{md_padding, Compress, MerkleDamgard};
+
 type Wu32 = W<u32>;
 
 pub const S0: [Wu32; 4] = [
@@ -13,11 +14,6 @@ pub const S0: [Wu32; 4] = [
     W(0x98BA_DCFE),
     W(0x1032_5476),
 ];
-const PADDING: [u8; 64] = [
-    0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0,
-];
 pub const K1: Wu32 = W(0x5A82_7999);
 pub const K2: Wu32 = W(0x6ED9_EBA1);
 
@@ -45,179 +41,116 @@ where
 {
     W(t.0.rotate_right(s)) - a - f(b, c, d) - k
 }
-pub fn md4(msg: &[u8]) -> String {
-    let mut blocks = Vec::new();
-    fn print_blocks(blocks: &Vec<Vec<Wu32>>) {
-        for block in blocks {
-            assert!(block.len() == 16);
-            for n in block {
-                let bytes = n.0.to_le_bytes();
-                println!(
-                    "{:0>2x}{:0>2x}{:0>2x}{:0>2x}",
-                    bytes[0], bytes[1], bytes[2], bytes[3]
-                );
-            }
-        }
-    }
-    fn add_u8_arr(arr: &[u8], blocks: &mut Vec<Vec<Wu32>>) -> usize {
-        let mut block = vec![W(0); 16];
-        if arr.len() < 64 {
-            return 0;
-        }
-        let mut cur = 0;
-        for i in (0..arr.len()).step_by(4) {
-            if i + 4 > arr.len() {
-                break;
-            }
-            block[cur] = W(u32::from_le_bytes([
-                arr[i + 0],
-                arr[i + 1],
-                arr[i + 2],
-                arr[i + 3],
-            ]));
-            cur += 1;
-            if cur == 16 {
-                blocks.push(block.clone());
-            }
-        }
-        cur
-    }
-    let cnt = add_u8_arr(msg, &mut blocks);
-    let msg_len = msg.len();
-    let remaining = msg_len - cnt * 4;
-    // padding to 448
-    let mut padding_block = msg[msg_len - remaining..msg_len]
-        .iter()
-        .map(|v| *v)
-        .collect::<Vec<_>>();
-    let padding_length = if padding_block.len() == 56 {
-        64
-    } else {
-        56 - padding_block.len()
-    };
-    padding_block = padding_block
-        .iter()
-        .chain(PADDING[0..padding_length].iter())
-        .map(|v| *v)
-        .collect();
-    // adding length
-    let length = ((msg.len() * 8) as u64).to_le_bytes();
-    padding_block = padding_block
-        .iter()
-        .chain(length.iter())
-        .map(|v| *v)
-        .collect();
-    add_u8_arr(&padding_block, &mut blocks);
-    assert!(padding_block.len() % 64 == 0);
-    // print_blocks(&blocks);
+pub fn md4(msg: &[u8]) -> [u8; 16] {
+    let mut hasher = Md4::new();
+    hasher.update(msg);
+    hasher.finalize()
+}
 
-    let mut states = S0;
-    for msg in blocks {
-        let [mut a, mut b, mut c, mut d] = states;
-        for &i in &[0, 4, 8, 12] {
-            a = op(f, a, b, c, d, msg[i], 3);
-            d = op(f, d, a, b, c, msg[i + 1], 7);
-            c = op(f, c, d, a, b, msg[i + 2], 11);
-            b = op(f, b, c, d, a, msg[i + 3], 19);
-        }
-        for &i in &[0, 1, 2, 3] {
-            a = op(g, a, b, c, d, msg[i] + K1, 3);
-            d = op(g, d, a, b, c, msg[i + 4] + K1, 5);
-            c = op(g, c, d, a, b, msg[i + 8] + K1, 9);
-            b = op(g, b, c, d, a, msg[i + 12] + K1, 13);
-        }
-        for &i in &[0, 2, 1, 3] {
-            a = op(h, a, b, c, d, msg[i] + K2, 3);
-            d = op(h, d, a, b, c, msg[i + 8] + K2, 9);
-            c = op(h, c, d, a, b, msg[i + 4] + K2, 11);
-            b = op(h, b, c, d, a, msg[i + 12] + K2, 15);
-        }
-        states[0] += a;
-        states[1] += b;
-        states[2] += c;
-        states[3] += d;
+// the glue padding appended to a message of `len` bytes
+pub fn md4_padding(len: usize) -> Vec<u8> {
+    md_padding(len, 64, 8, false)
+}
+
+pub fn digest_to_state(digest: &[u8; 16]) -> [Wu32; 4] {
+    let mut state = [W(0); 4];
+    for (i, c) in digest.chunks(4).enumerate() {
+        state[i] = W(u32::from_le_bytes(c.try_into().unwrap()));
     }
-    states
-        .iter()
-        .map(|v| v.0.to_le_bytes())
-        .collect::<Vec<_>>()
-        .concat()
-        .iter()
-        .fold(String::new(), |acc, v| format!("{acc}{v:x}"))
+    state
 }
 
-fn get_state(state: &mut State, msg: &[Wu32; 16]) {
-    let states = get_states_from(state.clone(), 1, msg);
-    state.val = states[0].val;
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Md4Compress;
+
+impl Compress for Md4Compress {
+    type State = [Wu32; 4];
+    const BLOCK_SIZE: usize = 64;
+    const BIG_ENDIAN: bool = false;
+
+    fn iv(&self) -> [Wu32; 4] {
+        S0
+    }
+    fn compress(&self, state: &mut [Wu32; 4], block: &[u8]) {
+        compress(state, &to_block(block))
+    }
+    fn to_bytes(&self, state: &[Wu32; 4]) -> Vec<u8> {
+        state.iter().flat_map(|v| v.0.to_le_bytes()).collect()
+    }
+    fn state_from_bytes(&self, bytes: &[u8]) -> [Wu32; 4] {
+        digest_to_state(bytes.try_into().unwrap())
+    }
 }
-// NOTE: only work in first round
-fn get_states_from(mut state: State, mut n: usize, msg: &[Wu32; 16]) -> Vec<State> {
-    let mut states = [State::default(); 4];
-    for (i, iv) in S0.iter().enumerate() {
-        states[i] = State {
-            typ: i.into(),
-            num: 0,
-            val: iv.clone(),
-        }
+
+#[derive(Clone)]
+pub struct Md4(MerkleDamgard<Md4Compress>);
+
+impl Md4 {
+    pub fn new() -> Self {
+        Self(MerkleDamgard::new(Md4Compress))
+    }
+    // resume hashing from an intermediate state, `len` is the number of bytes
+    // (padded to a block boundary) that produced `state`
+    pub fn with_state(state: [Wu32; 4], len: u64) -> Self {
+        Self(MerkleDamgard::with_state(Md4Compress, state, len))
+    }
+    pub fn state(&self) -> [Wu32; 4] {
+        self.0.state()
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
     }
-    let mut res = vec![];
-    while state.num == 0 && n > 0 {
-        res.push(states[state.typ as usize]);
+    pub fn finalize(self) -> [u8; 16] {
+        self.0.finalize().try_into().unwrap()
+    }
+}
 
-        state = state.next();
-        n -= 1;
+impl Default for Md4 {
+    fn default() -> Self {
+        Self::new()
     }
-    if n == 0 {
-        return res;
+}
+
+fn to_block(chunk: &[u8]) -> [Wu32; 16] {
+    let mut block = [W(0); 16];
+    for (i, c) in chunk.chunks(4).enumerate() {
+        block[i] = W(u32::from_le_bytes(c.try_into().unwrap()));
     }
-    assert!(state.num > 0 && n > 0);
-    let mut cur_state = State {
-        typ: StateType::A,
-        num: 1,
-        val: W(0),
-    };
+    block
+}
 
-    let mut i = 0;
-    while cur_state < state {
-        let ind = ORDER[i % 4] as usize;
-        let s = op(
-            f,
-            states[ind].val,
-            states[(ind + 1) % 4].val,
-            states[(ind + 2) % 4].val,
-            states[(ind + 3) % 4].val,
-            msg[i],
-            SHIFT1[i % 4],
-        );
-        states[ind].val = s;
-        i = i + 1;
-        cur_state = cur_state.next();
+pub fn compress(states: &mut [Wu32; 4], msg: &[Wu32; 16]) {
+    let [mut a, mut b, mut c, mut d] = *states;
+    for &i in &[0, 4, 8, 12] {
+        a = op(f, a, b, c, d, msg[i], 3);
+        d = op(f, d, a, b, c, msg[i + 1], 7);
+        c = op(f, c, d, a, b, msg[i + 2], 11);
+        b = op(f, b, c, d, a, msg[i + 3], 19);
     }
-    while n > 0 {
-        let ind = ORDER[i % 4] as usize;
-        let s = op(
-            f,
-            states[ind].val,
-            states[(ind + 1) % 4].val,
-            states[(ind + 2) % 4].val,
-            states[(ind + 3) % 4].val,
-            msg[i],
-            SHIFT1[i % 4],
-        );
-        states[ind].val = s;
-        state.val = s;
-        res.push(state.clone());
-        i = i + 1;
-        state = state.next();
-        n -= 1;
+    for &i in &[0, 1, 2, 3] {
+        a = op(g, a, b, c, d, msg[i] + K1, 3);
+        d = op(g, d, a, b, c, msg[i + 4] + K1, 5);
+        c = op(g, c, d, a, b, msg[i + 8] + K1, 9);
+        b = op(g, b, c, d, a, msg[i + 12] + K1, 13);
     }
-    res
+    for &i in &[0, 2, 1, 3] {
+        a = op(h, a, b, c, d, msg[i] + K2, 3);
+        d = op(h, d, a, b, c, msg[i + 8] + K2, 9);
+        c = op(h, c, d, a, b, msg[i + 4] + K2, 11);
+        b = op(h, b, c, d, a, msg[i + 12] + K2, 15);
+    }
+    states[0] += a;
+    states[1] += b;
+    states[2] += c;
+    states[3] += d;
 }
+
 #[cfg(test)]
 mod tests {
-    use state::State;
+    use crate::misc::to_hex;
 
     use super::*;
+
     #[test]
     fn test_md4_impl() {
         let messages = [
@@ -243,69 +176,57 @@ mod tests {
             "a25f98cb8736de9f7c9641995982a44f",
         ];
         for (msg, expect) in messages.iter().zip(known_hashes.iter()) {
-            let got = md4(msg);
+            let got = to_hex(&md4(msg));
             assert_eq!(&got, expect);
         }
     }
     #[test]
-    fn test_get_state() {
-        let states: [u32; 20] = [
-            0x67452301, 0x10325476, 0x98badcfe, 0xefcdab89, 0xffffffb7, 0x1f80, 0x44e430c4,
-            0x59dd534c, 0x420c7d2, 0x626141a0, 0x4104af5, 0xc742bff0, 0x4260f609, 0x5b3079d4,
-            0x3801a653, 0x2310d19, 0x691356ec, 0x950ef735, 0x8a67f1d9, 0x966b1a40,
+    fn test_md4_rfc1320() {
+        let vectors: [(&[u8], &str); 7] = [
+            (b"", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            (b"a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            (b"abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            (b"message digest", "d9130a8164549fe818874806e1c7014b"),
+            (
+                b"abcdefghijklmnopqrstuvwxyz",
+                "d79e1c308aa5bbcdeea8ed63df412da9",
+            ),
+            (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "043f8582f241db351ce627e153e7f0e4",
+            ),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "e33b4ddc9c38f2199c3e7b164fcc0536",
+            ),
         ];
-        let msg = [
-            W(0xfffffff7),
-            W(0x0),
-            W(0x8fffffff),
-            W(0xff7fffff),
-            W(0xffbffc7f),
-            W(0x2fbf),
-            W(0xfffffd79),
-            W(0xfdfffffa),
-            W(0xa0bff),
-            W(0x4605f),
-            W(0xfff3bf1f),
-            W(0xffffeffe),
-            W(0xf1bfffff),
-            W(0xffe7ffff),
-            W(0xfffc487f),
-            W(0x80000e7f),
-        ];
-        for i in 0..=4 {
-            for t in ORDER {
-                let mut state = State {
-                    typ: t,
-                    num: i,
-                    val: W(0),
-                };
-                get_state(&mut state, &msg);
-                let expect = states[state.num as usize * 4 + ORDER[state.typ as usize] as usize];
-                assert_eq!(
-                    state.val.0, expect,
-                    "diff on i: {:?}, expect: 0x{:x}",
-                    state, expect
-                );
-            }
-        }
-        for i in 0..=3 {
-            for t in ORDER {
-                let mut state = State {
-                    typ: t,
-                    num: i,
-                    val: W(0),
-                };
-                let res = get_states_from(state, 4, &msg);
-                let start_ind = state.num as usize * 4 + ORDER[state.typ as usize] as usize;
-                let expects = &states[start_ind..start_ind + 4];
-                for (expect, got) in expects.iter().zip(res) {
-                    assert_eq!(
-                        got.val.0, *expect,
-                        "diff on i: {:?}, expect: 0x{:x}",
-                        got, expect
-                    );
-                }
+        for (msg, expect) in vectors {
+            assert_eq!(to_hex(&md4(msg)), expect);
+
+            // feeding the message in uneven pieces gives the same digest
+            let mut hasher = Md4::new();
+            for chunk in msg.chunks(7) {
+                hasher.update(chunk);
             }
+            assert_eq!(to_hex(&hasher.finalize()), expect);
         }
     }
+
+    #[test]
+    fn test_md4_with_state() {
+        let prefix = [0x61u8; 64];
+        let suffix = b"the rest of the message";
+        let mut hasher = Md4::new();
+        hasher.update(&prefix);
+        let mut resumed = Md4::with_state(hasher.state(), 64);
+        resumed.update(suffix);
+        hasher.update(suffix);
+        assert_eq!(resumed.finalize(), hasher.finalize());
+
+        let digest = Md4::new().finalize();
+        let mut hasher = Md4::with_state(digest_to_state(&digest), 64);
+        hasher.update(b"extension");
+        let expect = md4(&[&md4_padding(0)[..], b"extension"].concat());
+        assert_eq!(hasher.finalize(), expect);
+    }
 }
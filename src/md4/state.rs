@@ -9,27 +9,6 @@ pub enum StateType {
     D,
 }
 
-impl StateType {
-    pub fn prev(&self) -> StateType {
-        use StateType::*;
-        match self {
-            A => B,
-            B => C,
-            C => D,
-            D => A,
-        }
-    }
-    pub fn next(&self) -> StateType {
-        use StateType::*;
-        match self {
-            A => D,
-            B => A,
-            C => B,
-            D => C,
-        }
-    }
-}
-
 impl PartialOrd for StateType {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         let order = [0, 3, 2, 1];
@@ -65,44 +44,15 @@ pub struct State {
     pub val: Wu32,
 }
 impl State {
-    pub fn prev_n(&self, mut n: usize) -> State {
-        let mut typ = self.typ.clone();
-        let mut num = self.num;
-        while n > 0 {
-            if typ == StateType::A {
-                assert!(n > 0, "A0 doesn't have previous state");
-                num -= 1;
-            }
-            typ = typ.prev();
-            n -= 1;
-        }
-        State {
-            typ,
-            num,
-            val: W(0),
-        }
-    }
-    pub fn next_n(&self, mut n: usize) -> State {
-        let mut typ = self.typ.clone();
-        let mut num = self.num;
-        while n > 0 {
-            if typ == StateType::B {
-                num += 1;
-            }
-            typ = typ.next();
-            n -= 1;
-        }
-        State {
-            typ,
-            num,
-            val: W(0),
-        }
-    }
-    pub fn prev(&self) -> State {
-        self.prev_n(1)
-    }
-    pub fn next(&self) -> State {
-        self.next_n(1)
+    // position in the order the states are computed: a0, d0, c0, b0, a1, ...
+    pub fn index(&self) -> usize {
+        let pos = match self.typ {
+            StateType::A => 0,
+            StateType::D => 1,
+            StateType::C => 2,
+            StateType::B => 3,
+        };
+        self.num as usize * 4 + pos
     }
 }
 impl core::fmt::Debug for State {
@@ -0,0 +1,536 @@
+#![allow(unused)]
+// A port of Patrick Stach's md5coll.c, which implements the two-block MD5
+// collision of Wang et al. The first block leaves the chaining values with the
+// difference (2^31, 2^31 + 2^25, 2^31 + 2^25, 2^31 + 2^25), the second block
+// cancels it out.
+//
+// Q_j is the state word computed in step j (1..=64), Q_-3..Q_0 are the
+// chaining value. Message words of the first 16 steps are solved backwards
+// from randomly chosen Q_j that already satisfy the sufficient conditions,
+// the later steps are checked as they are computed.
+use std::ops::{Index, IndexMut};
+
+use super::{compress, step_params, to_block, T};
+use crate::random::{os, RandomSource};
+
+// libc's random() only returns 31 bits, the masks below are written for it
+fn random(rng: &mut impl RandomSource) -> u32 {
+    rng.next_u32() >> 1
+}
+
+#[derive(Clone, Copy)]
+struct Trace {
+    // q[j + 3] holds Q_j, so the chaining value (a, d, c, b) sits in q[0..4]
+    q: [u32; 68],
+    x: [u32; 16],
+}
+
+impl Trace {
+    fn new(iv: [u32; 4]) -> Self {
+        let mut q = [0; 68];
+        q[0] = iv[0];
+        q[1] = iv[3];
+        q[2] = iv[2];
+        q[3] = iv[1];
+        Self { q, x: [0; 16] }
+    }
+    // compute Q_j from Q_{j-4}..Q_{j-1} and the message
+    fn forward(&mut self, j: usize) -> u32 {
+        let (fun, k, s) = step_params(j - 1);
+        let [a, d, c, b] = [self.q[j - 1], self.q[j], self.q[j + 1], self.q[j + 2]];
+        self.q[j + 3] = b.wrapping_add(
+            fun(b, c, d)
+                .wrapping_add(a)
+                .wrapping_add(self.x[k])
+                .wrapping_add(T[j - 1])
+                .rotate_left(s),
+        );
+        self.q[j + 3]
+    }
+    // solve the message word of step j from Q_{j-4}..Q_j
+    fn backward(&mut self, j: usize) -> u32 {
+        let (fun, k, s) = step_params(j - 1);
+        let [a, d, c, b, v] = [
+            self.q[j - 1],
+            self.q[j],
+            self.q[j + 1],
+            self.q[j + 2],
+            self.q[j + 3],
+        ];
+        self.x[k] = v
+            .wrapping_sub(b)
+            .rotate_right(s)
+            .wrapping_sub(fun(b, c, d))
+            .wrapping_sub(a)
+            .wrapping_sub(T[j - 1]);
+        self.x[k]
+    }
+    // the chaining value word updated by step j (61..=64), i.e. A, D, C or B
+    fn head(&self, j: usize) -> u32 {
+        self.q[j - 61].wrapping_add(self.q[j + 3])
+    }
+    fn chaining(&self) -> [u32; 4] {
+        [self.head(61), self.head(64), self.head(63), self.head(62)]
+    }
+}
+
+impl Index<usize> for Trace {
+    type Output = u32;
+    fn index(&self, j: usize) -> &Self::Output {
+        &self.q[j + 3]
+    }
+}
+
+impl IndexMut<usize> for Trace {
+    fn index_mut(&mut self, j: usize) -> &mut Self::Output {
+        &mut self.q[j + 3]
+    }
+}
+
+// how a state word Q_j of the first 16 steps is chosen:
+// Q_j = ((random | set) & !(unset | copy)) | (Q_{j-1} & copy), Q'_j = Q_j - diff,
+// then the message words solved from both traces must differ by `x_diff`
+struct Pick {
+    j: usize,
+    set: u32,
+    unset: u32,
+    copy: u32,
+    diff: u32,
+    x_diff: Option<u32>,
+}
+
+const fn pick(j: usize, set: u32, unset: u32, copy: u32, diff: u32, x_diff: Option<u32>) -> Pick {
+    Pick {
+        j,
+        set,
+        unset,
+        copy,
+        diff,
+        x_diff,
+    }
+}
+
+const BLOCK1_PICKS: [Pick; 14] = [
+    pick(3, 0, 0x00800040, 0, 0, None),
+    pick(4, 0x80080800, 0x00800040, 0x0077f780, 0, None),
+    pick(5, 0x88400025, 0x02bfffc0, 0, 0x00000040, None),
+    pick(6, 0x027fbc41, 0x888043a4, 0x7500001a, 0x7f800040, None),
+    pick(7, 0x03fef820, 0xfc0107df, 0, 0x07800041, Some(0)),
+    pick(8, 0x01910540, 0xfe0eaabf, 0, 0x00827fff, Some(0)),
+    pick(9, 0xfb102f3d, 0x040f80c2, 0x00001000, 0x8000003f, Some(0)),
+    pick(10, 0x401f9040, 0x80802183, 0, 0x7ffff000, Some(0)),
+    pick(11, 0x000180c2, 0xc00e3101, 0x00004000, 0x40000000, Some(0)),
+    pick(
+        12,
+        0x00081100,
+        0xc007e080,
+        0x03000000,
+        0x80002080,
+        Some(0x00008000),
+    ),
+    pick(13, 0x410fe008, 0x82000180, 0, 0x7f000000, Some(0)),
+    pick(14, 0x000be188, 0xa3040000, 0, 0x80000000, Some(0)),
+    pick(15, 0x21008000, 0x82000008, 0, 0x80007ff8, Some(0x80000000)),
+    pick(16, 0x20000000, 0x80000000, 0, 0xa0000000, Some(0)),
+];
+const BLOCK1_A5: Pick = pick(17, 0, 0x80020000, 0x00008008, 0x80000000, Some(0));
+const BLOCK1_B5: Pick = pick(20, 0, 0x80000000, 0, 0x80000000, Some(0));
+
+const BLOCK2_A1: Pick = pick(1, 0x84200000, 0x0a000820, 0, 0x7e000000, Some(0));
+const BLOCK2_PICKS: [Pick; 14] = [
+    pick(2, 0x8c000800, 0x02208026, 0x701f10c0, 0x7dffffe0, Some(0)),
+    pick(3, 0xbe1f0966, 0x40201080, 0x00000018, 0x7dfef7e0, Some(0)),
+    pick(4, 0xba040010, 0x443b19ee, 0x00000601, 0x7dffffe2, Some(0)),
+    pick(5, 0x482f0e50, 0xb41011af, 0, 0x7ffffcbf, Some(0x80000000)),
+    pick(6, 0x04220c56, 0x9a1113a9, 0, 0x80110000, Some(0)),
+    pick(7, 0x96011e01, 0x083201c0, 0x01808000, 0x88000040, Some(0)),
+    pick(8, 0x843283c0, 0x1b810001, 0x00000002, 0x80818000, Some(0)),
+    pick(9, 0x9c0101c1, 0x03828202, 0x00001000, 0x7fffffbf, Some(0)),
+    pick(10, 0x878383c0, 0x00041003, 0, 0x7ffff000, Some(0)),
+    pick(11, 0x800583c3, 0x00021000, 0x00086000, 0x80000000, Some(0)),
+    pick(
+        12,
+        0x80081080,
+        0x0007e000,
+        0x7f000000,
+        0x80002080,
+        Some(0x00008000),
+    ),
+    pick(13, 0x3f0fe008, 0x80000080, 0, 0x7f000000, Some(0)),
+    pick(14, 0x400be088, 0xbf040000, 0, 0x80000000, Some(0)),
+    pick(15, 0x7d000000, 0x82008008, 0, 0x7fff7ff8, Some(0x80000000)),
+];
+const BLOCK2_B4: Pick = pick(16, 0x20000000, 0x80000000, 0, 0xa0000000, Some(0));
+
+const LOOP_11: usize = 300;
+const LOOP_12: usize = 0x20000000;
+const LOOP_21: usize = 1000;
+const LOOP_22: usize = 0x4000000;
+
+// bits of Q_16 flipped while searching the second block, bit 29 and 31 are
+// fixed by the conditions
+const MASK22: [u32; 30] = {
+    let mut mask = [0; 30];
+    let mut i = 0;
+    while i < 30 {
+        mask[i] = 1 << if i < 29 { i } else { 30 };
+        i += 1;
+    }
+    mask
+};
+
+fn choose(rng: &mut impl RandomSource, t0: &mut Trace, t1: &mut Trace, p: &Pick) -> bool {
+    let j = p.j;
+    t0[j] = ((random(rng) | p.set) & !(p.unset | p.copy)) | (t0[j - 1] & p.copy);
+    set_diff(t0, t1, j, p)
+}
+
+// Q_j has been changed, re-solve the message word and check its difference
+fn set_diff(t0: &mut Trace, t1: &mut Trace, j: usize, p: &Pick) -> bool {
+    t1[j] = t0[j].wrapping_sub(p.diff);
+    match p.x_diff {
+        Some(diff) => t0.backward(j) ^ t1.backward(j) == diff,
+        None => true,
+    }
+}
+
+fn block1_step(t0: &mut Trace, t1: &mut Trace, j: usize) -> bool {
+    let q = t0.forward(j);
+    let ok = match j {
+        18 => q & 0xa0020000 == 0x00020000 | (t0[17] & 0x20000000),
+        19 => q & 0x80020000 == 0,
+        21 => q & 0x80020000 == t0[20] & 0x00020000,
+        22 | 23 => q & 0x80000000 == 0,
+        48 | 49 | 51..=59 => (q ^ t0[j - 2]) & 0x80000000 == 0,
+        50 => (q ^ t0[48]) & 0x80000000 != 0,
+        60 => q & 0x02000000 == 0,
+        62 => t0.head(62) & 0x02000000 == 0,
+        63 => t0.head(63) & 0x86000000 == (t0.head(62) & 0x80000000) | 0x02000000,
+        64 => t0.head(64) & 0x86000020 == t0.head(63) & 0x80000000,
+        _ => true,
+    };
+    if !ok {
+        return false;
+    }
+    let q1 = t1.forward(j);
+    match j {
+        19 => q.wrapping_sub(q1) == 0x7ffe0000,
+        23..=34 => q == q1,
+        61 => t0.head(61) ^ t1.head(61) == 0x80000000,
+        62..=64 => t0.head(j).wrapping_sub(t1.head(j)) == 0x7e000000,
+        _ => q ^ q1 == 0x80000000,
+    }
+}
+
+fn block2_step(t0: &mut Trace, t1: &mut Trace, j: usize) -> bool {
+    let q = t0.forward(j);
+    let ok = match j {
+        17 => q & 0x80028008 == t0[16] & 0x00008008,
+        18 => q & 0xa0020000 == (t0[17] & 0x20000000) | 0x00020000,
+        19 => q & 0x80020000 == 0,
+        20 | 22 | 23 => q & 0x80000000 == 0,
+        21 => q & 0x80020000 == t0[20] & 0x00020000,
+        48 | 49 | 51..=59 => (q ^ t0[j - 2]) & 0x80000000 == 0,
+        _ => true,
+    };
+    if !ok {
+        return false;
+    }
+    let q1 = t1.forward(j);
+    match j {
+        19 => q.wrapping_sub(q1) == 0x7ffe0000,
+        23..=34 => q == q1,
+        61 => q ^ q1 == 0x80000000 && t0.head(61) == t1.head(61),
+        62..=64 => t0.head(j) == t1.head(j),
+        _ => q ^ q1 == 0x80000000,
+    }
+}
+
+// Q_20 has been chosen, which fixes X_0 and with it Q_1, Q_2 and X_2..X_5
+fn block1_first_words(t0: &mut Trace, t1: &mut Trace) -> bool {
+    t0.forward(1);
+    t1[1] = t0[1];
+    t0.forward(2);
+    t1[2] = t0[2];
+    t1.x[2] = t0.backward(3);
+    t1.x[3] = t0.backward(4);
+    if t0.backward(5) ^ t1.backward(5) != 0x80000000 {
+        return false;
+    }
+    t0.backward(6) == t1.backward(6)
+}
+
+fn block1(rng: &mut impl RandomSource, iv: [u32; 4]) -> (Trace, Trace) {
+    'block1_again: loop {
+        let mut t0 = Trace::new(iv);
+        let mut t1 = Trace::new(iv);
+        while !BLOCK1_PICKS
+            .iter()
+            .all(|p| choose(rng, &mut t0, &mut t1, p))
+        {}
+
+        let mut found = false;
+        for _ in 0..LOOP_11 {
+            if !choose(rng, &mut t0, &mut t1, &BLOCK1_A5)
+                || !(18..=19).all(|j| block1_step(&mut t0, &mut t1, j))
+                || !choose(rng, &mut t0, &mut t1, &BLOCK1_B5)
+                || !block1_first_words(&mut t0, &mut t1)
+                || !(21..=27).all(|j| block1_step(&mut t0, &mut t1, j))
+            {
+                continue;
+            }
+            found = true;
+            break;
+        }
+        if !found {
+            continue 'block1_again;
+        }
+        if let Some(traces) = block1_search(rng, t0, t1) {
+            return traces;
+        }
+    }
+}
+
+// Q_1..Q_20 satisfy their conditions, flip random bits of Q_20 until the
+// rest of the block holds too
+fn block1_search(
+    rng: &mut impl RandomSource,
+    mut t0: Trace,
+    mut t1: Trace,
+) -> Option<(Trace, Trace)> {
+    for _ in 0..LOOP_12 {
+        t0[20] ^= 1 << (random(rng) % 31);
+        if set_diff(&mut t0, &mut t1, 20, &BLOCK1_B5)
+            && block1_first_words(&mut t0, &mut t1)
+            && (21..=64).all(|j| block1_step(&mut t0, &mut t1, j))
+        {
+            return Some((t0, t1));
+        }
+    }
+    None
+}
+
+fn block2(rng: &mut impl RandomSource, iv0: [u32; 4], iv1: [u32; 4]) -> (Trace, Trace) {
+    'block2_again: loop {
+        let mut t0 = Trace::new(iv0);
+        let mut t1 = Trace::new(iv1);
+        while !choose(rng, &mut t0, &mut t1, &BLOCK2_A1) {}
+        for p in BLOCK2_PICKS.iter() {
+            if !(0..10).any(|_| choose(rng, &mut t0, &mut t1, p)) {
+                continue 'block2_again;
+            }
+        }
+
+        let mut found = false;
+        for _ in 0..LOOP_21 {
+            if choose(rng, &mut t0, &mut t1, &BLOCK2_B4)
+                && (17..=21).all(|j| block2_step(&mut t0, &mut t1, j))
+            {
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            continue 'block2_again;
+        }
+        if let Some(traces) = block2_search(rng, t0, t1) {
+            return traces;
+        }
+    }
+}
+
+// Q_1..Q_21 satisfy their conditions, flip random bits of Q_16 until the
+// rest of the block holds too
+fn block2_search(
+    rng: &mut impl RandomSource,
+    mut t0: Trace,
+    mut t1: Trace,
+) -> Option<(Trace, Trace)> {
+    for _ in 0..LOOP_22 {
+        t0[16] ^= MASK22[random(rng) as usize % 30];
+        if set_diff(&mut t0, &mut t1, 16, &BLOCK2_B4)
+            && (17..=64).all(|j| block2_step(&mut t0, &mut t1, j))
+        {
+            return Some((t0, t1));
+        }
+    }
+    None
+}
+
+fn to_bytes(x: &[u32; 16]) -> Vec<u8> {
+    x.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+// search two 128-byte messages that collide when compressed from `iv`
+pub fn find_collision(iv: [u32; 4]) -> ([u8; 128], [u8; 128]) {
+    find_collision_with(&mut os(), iv)
+}
+
+pub fn find_collision_with(rng: &mut impl RandomSource, iv: [u32; 4]) -> ([u8; 128], [u8; 128]) {
+    let (b0, b1) = block1(rng, iv);
+    let (c0, c1) = block2(rng, b0.chaining(), b1.chaining());
+    let m0 = [to_bytes(&b0.x), to_bytes(&c0.x)].concat();
+    let m1 = [to_bytes(&b1.x), to_bytes(&c1.x)].concat();
+    (m0.try_into().unwrap(), m1.try_into().unwrap())
+}
+
+// the chaining value after compressing whole blocks of `msg` from `iv`
+pub fn chaining_value(iv: [u32; 4], msg: &[u8]) -> [u32; 4] {
+    assert!(msg.len().is_multiple_of(64));
+    let mut state = iv;
+    for chunk in msg.chunks(64) {
+        compress(&mut state, &to_block(chunk));
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        md5::{md5, IV},
+        misc::from_hex,
+        random::seeded,
+    };
+
+    use rand::rngs::mock::StepRng;
+
+    use super::*;
+
+    #[test]
+    fn test_trace() {
+        let msg = crate::misc::rand_vec(64);
+        let mut t = Trace::new(IV);
+        t.x = to_block(&msg);
+        for j in 1..=64 {
+            t.forward(j);
+        }
+        let mut state = IV;
+        compress(&mut state, &t.x);
+        assert_eq!(t.chaining(), state);
+
+        // solving the message words back from the first 16 states
+        let mut solved = Trace::new(IV);
+        solved.q = t.q;
+        for j in 1..=16 {
+            solved.backward(j);
+        }
+        assert_eq!(solved.x, t.x);
+    }
+
+    // the published collision of Wang et al., the first block of each message
+    // then the second one
+    const WANG: [&str; 2] = [
+        "d131dd02c5e6eec4693d9a0698aff95c2fcab58712467eab4004583eb8fb7f89\
+         55ad340609f4b30283e488832571415a085125e8f7cdc99fd91dbdf280373c5b\
+         d8823e3156348f5bae6dacd436c919c6dd53e2b487da03fd02396306d248cda0\
+         e99f33420f577ee8ce54b67080a80d1ec69821bcb6a8839396f9652b6ff72a70",
+        "d131dd02c5e6eec4693d9a0698aff95c2fcab50712467eab4004583eb8fb7f89\
+         55ad340609f4b30283e4888325f1415a085125e8f7cdc99fd91dbd7280373c5b\
+         d8823e3156348f5bae6dacd436c919c6dd53e23487da03fd02396306d248cda0\
+         e99f33420f577ee8ce54b67080280d1ec69821bcb6a8839396f965ab6ff72a70",
+    ];
+
+    // the traces of one block of both messages, with the first 16 states
+    // computed from the message words
+    fn traces(iv0: [u32; 4], iv1: [u32; 4], b0: &[u8], b1: &[u8]) -> (Trace, Trace) {
+        let mut t0 = Trace::new(iv0);
+        let mut t1 = Trace::new(iv1);
+        t0.x = to_block(b0);
+        t1.x = to_block(b1);
+        for j in 1..=16 {
+            t0.forward(j);
+            t1.forward(j);
+        }
+        (t0, t1)
+    }
+
+    // the conditions `choose` builds into Q_j, and the differences it checks
+    fn holds(t0: &mut Trace, t1: &mut Trace, p: &Pick) -> bool {
+        let q = t0[p.j];
+        q & p.set == p.set
+            && q & p.unset == 0
+            && (q ^ t0[p.j - 1]) & p.copy == 0
+            && t0[p.j].wrapping_sub(t1[p.j]) == p.diff
+            && p.x_diff
+                .is_none_or(|d| t0.backward(p.j) ^ t1.backward(p.j) == d)
+    }
+
+    #[test]
+    fn test_conditions_on_wang_collision() {
+        let [m0, m1] = WANG.map(|m| from_hex(m).unwrap());
+        assert_eq!(md5(&m0), md5(&m1));
+
+        let (mut t0, mut t1) = traces(IV, IV, &m0[..64], &m1[..64]);
+        // Q_17 and Q_20 are chosen with the first 16, the step function
+        // recomputes them from the message here
+        for j in 17..=20 {
+            t0.forward(j);
+            t1.forward(j);
+        }
+        assert!(BLOCK1_PICKS.iter().all(|p| holds(&mut t0, &mut t1, p)));
+        assert!(holds(&mut t0, &mut t1, &BLOCK1_A5));
+        assert!(holds(&mut t0, &mut t1, &BLOCK1_B5));
+        assert!((17..=64).all(|j| block1_step(&mut t0, &mut t1, j)));
+
+        let (iv0, iv1) = (t0.chaining(), t1.chaining());
+        assert_ne!(iv0, iv1);
+        let (mut t0, mut t1) = traces(iv0, iv1, &m0[64..], &m1[64..]);
+        assert!(holds(&mut t0, &mut t1, &BLOCK2_A1));
+        assert!(BLOCK2_PICKS.iter().all(|p| holds(&mut t0, &mut t1, p)));
+        assert!(holds(&mut t0, &mut t1, &BLOCK2_B4));
+        assert!((17..=64).all(|j| block2_step(&mut t0, &mut t1, j)));
+        assert_eq!(t0.chaining(), t1.chaining());
+    }
+
+    #[test]
+    fn test_search_replays_wang_collision() {
+        let [m0, m1] = WANG.map(|m| from_hex(m).unwrap());
+        // A generator stuck on one value flips the same bit every time: the
+        // first flip moves away from the published blocks and the second one
+        // brings them back, which the search has to recognise. `random` drops
+        // the lowest bit, this flips bit 8.
+        let mut rng = StepRng::new(8 << 1, 0);
+
+        let (mut t0, mut t1) = traces(IV, IV, &m0[..64], &m1[..64]);
+        for j in 17..=20 {
+            t0.forward(j);
+            t1.forward(j);
+        }
+        let (b0, b1) = block1_search(&mut rng, t0, t1).unwrap();
+        assert_eq!(to_bytes(&b0.x), m0[..64]);
+        assert_eq!(to_bytes(&b1.x), m1[..64]);
+
+        let (mut t0, mut t1) = traces(b0.chaining(), b1.chaining(), &m0[64..], &m1[64..]);
+        for j in 17..=21 {
+            t0.forward(j);
+            t1.forward(j);
+        }
+        let (c0, c1) = block2_search(&mut rng, t0, t1).unwrap();
+        assert_eq!(to_bytes(&c0.x), m0[64..]);
+        assert_eq!(to_bytes(&c1.x), m1[64..]);
+        assert_eq!(c0.chaining(), c1.chaining());
+    }
+
+    // the search takes tens of minutes, run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_find_collision() {
+        let (m0, m1) = find_collision(IV);
+        assert_ne!(m0, m1);
+        assert_eq!(md5(&m0), md5(&m1));
+        // colliding blocks can be followed by any common suffix
+        assert_eq!(
+            md5(&[&m0[..], b"suffix"].concat()),
+            md5(&[&m1[..], b"suffix"].concat())
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_find_collision_with_iv() {
+        let iv = [0x01234567, 0x89abcdef, 0xfedcba98, 0x76543210];
+        let (m0, m1) = find_collision_with(&mut seeded(17), iv);
+        assert_ne!(m0, m1);
+        assert_eq!(chaining_value(iv, &m0), chaining_value(iv, &m1));
+    }
+}
@@ -0,0 +1,203 @@
+#![allow(unused)]
+pub mod collision;
+
+use crate::merkle_damgard::{md_padding, Compress, MerkleDamgard};
+
+pub const IV: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+// additive constants, floor(abs(sin(i + 1)) * 2^32)
+pub const T: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+pub const SHIFT: [[u32; 4]; 4] = [
+    [7, 12, 17, 22],
+    [5, 9, 14, 20],
+    [4, 11, 16, 23],
+    [6, 10, 15, 21],
+];
+
+pub fn f(x: u32, y: u32, z: u32) -> u32 {
+    z ^ (x & (y ^ z))
+}
+pub fn g(x: u32, y: u32, z: u32) -> u32 {
+    f(z, x, y)
+}
+pub fn h(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+pub fn i(x: u32, y: u32, z: u32) -> u32 {
+    y ^ (x | !z)
+}
+
+type BoolFn = fn(u32, u32, u32) -> u32;
+
+// boolean function, message word index and rotation of step `i` (0..64)
+pub fn step_params(i: usize) -> (BoolFn, usize, u32) {
+    let round = i / 16;
+    let shift = SHIFT[round][i % 4];
+    match round {
+        0 => (f, i, shift),
+        1 => (g, (5 * i + 1) % 16, shift),
+        2 => (h, (3 * i + 5) % 16, shift),
+        3 => (self::i, (7 * i) % 16, shift),
+        _ => unreachable!(),
+    }
+}
+
+pub fn md5(message: &[u8]) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    hasher.update(message);
+    hasher.finalize()
+}
+
+// the glue padding appended to a message of `len` bytes
+pub fn md5_padding(len: usize) -> Vec<u8> {
+    md_padding(len, 64, 8, false)
+}
+
+pub fn digest_to_state(digest: &[u8; 16]) -> [u32; 4] {
+    let mut state = [0; 4];
+    for (i, c) in digest.chunks(4).enumerate() {
+        state[i] = u32::from_le_bytes(c.try_into().unwrap());
+    }
+    state
+}
+
+pub fn compress(state: &mut [u32; 4], block: &[u32; 16]) {
+    let [mut a, mut b, mut c, mut d] = *state;
+    for (i, t) in T.iter().enumerate() {
+        let (fun, k, s) = step_params(i);
+        let v = b.wrapping_add(
+            fun(b, c, d)
+                .wrapping_add(a)
+                .wrapping_add(block[k])
+                .wrapping_add(*t)
+                .rotate_left(s),
+        );
+        (a, b, c, d) = (d, v, b, c);
+    }
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+}
+
+pub fn to_block(chunk: &[u8]) -> [u32; 16] {
+    let mut block = [0; 16];
+    for (i, c) in chunk.chunks(4).enumerate() {
+        block[i] = u32::from_le_bytes(c.try_into().unwrap());
+    }
+    block
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Md5Compress;
+
+impl Compress for Md5Compress {
+    type State = [u32; 4];
+    const BLOCK_SIZE: usize = 64;
+    const BIG_ENDIAN: bool = false;
+
+    fn iv(&self) -> [u32; 4] {
+        IV
+    }
+    fn compress(&self, state: &mut [u32; 4], block: &[u8]) {
+        compress(state, &to_block(block))
+    }
+    fn to_bytes(&self, state: &[u32; 4]) -> Vec<u8> {
+        state.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+    fn state_from_bytes(&self, bytes: &[u8]) -> [u32; 4] {
+        digest_to_state(bytes.try_into().unwrap())
+    }
+}
+
+#[derive(Clone)]
+pub struct Md5(MerkleDamgard<Md5Compress>);
+
+impl Md5 {
+    pub fn new() -> Self {
+        Self(MerkleDamgard::new(Md5Compress))
+    }
+    pub fn with_state(state: [u32; 4], len: u64) -> Self {
+        Self(MerkleDamgard::with_state(Md5Compress, state, len))
+    }
+    pub fn state(&self) -> [u32; 4] {
+        self.0.state()
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+    pub fn finalize(self) -> [u8; 16] {
+        self.0.finalize().try_into().unwrap()
+    }
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::misc::to_hex;
+
+    use super::*;
+
+    #[test]
+    fn test_md5_rfc1321() {
+        let vectors: [(&[u8], &str); 7] = [
+            (b"", "d41d8cd98f00b204e9800998ecf8427e"),
+            (b"a", "0cc175b9c0f1b6a831c399e269772661"),
+            (b"abc", "900150983cd24fb0d6963f7d28e17f72"),
+            (b"message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+            (
+                b"abcdefghijklmnopqrstuvwxyz",
+                "c3fcd3d76192e4007dfb496cca67e13b",
+            ),
+            (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "d174ab98d277d9f5a5611c2c9f419d9f",
+            ),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "57edf4a22be3c955ac49da2e2107b67a",
+            ),
+        ];
+        for (msg, expect) in vectors {
+            assert_eq!(to_hex(&md5(msg)), expect);
+
+            let mut hasher = Md5::new();
+            for chunk in msg.chunks(7) {
+                hasher.update(chunk);
+            }
+            assert_eq!(to_hex(&hasher.finalize()), expect);
+        }
+    }
+
+    #[test]
+    fn test_md5_with_state() {
+        let msg = [0x61u8; 100];
+        let mut hasher = Md5::new();
+        hasher.update(&msg[..64]);
+        let mut resumed = Md5::with_state(hasher.state(), 64);
+        resumed.update(&msg[64..]);
+        assert_eq!(resumed.finalize(), md5(&msg));
+
+        let digest = md5(b"");
+        let mut hasher = Md5::with_state(digest_to_state(&digest), 64);
+        hasher.update(b"extension");
+        assert_eq!(
+            hasher.finalize(),
+            md5(&[&md5_padding(0)[..], b"extension"].concat())
+        );
+    }
+}
@@ -0,0 +1,244 @@
+#![allow(unused)]
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+    aes::{cipher, key_expansion},
+    random::{os, RandomSource},
+};
+
+// A compression function and everything the Merkle-Damgard construction needs
+// to know about it: the block and state it works on, the initial state and
+// how the length is encoded in the padding.
+pub trait Compress: Clone {
+    type State: Copy + PartialEq + std::fmt::Debug;
+    const BLOCK_SIZE: usize;
+    // the length in bits is appended big endian (SHA) or little endian (MD4)
+    const BIG_ENDIAN: bool;
+    // bytes of the length field, SHA-384 and SHA-512 take 16
+    const LENGTH_SIZE: usize = 8;
+
+    fn iv(&self) -> Self::State;
+    fn compress(&self, state: &mut Self::State, block: &[u8]);
+    fn to_bytes(&self, state: &Self::State) -> Vec<u8>;
+    fn state_from_bytes(&self, bytes: &[u8]) -> Self::State;
+}
+
+// MD-strengthening: a one bit, zeros and the length of the message in bits
+// taking `length_size` bytes, so that the padded message ends on a block
+// boundary
+pub fn md_padding(len: usize, block_size: usize, length_size: usize, big_endian: bool) -> Vec<u8> {
+    let mut padding = vec![0x80];
+    while (len + padding.len()) % block_size != block_size - length_size {
+        padding.push(0x00);
+    }
+    let bits = len as u128 * 8;
+    if big_endian {
+        padding.extend(&bits.to_be_bytes()[16 - length_size..]);
+    } else {
+        padding.extend(&bits.to_le_bytes()[..length_size]);
+    }
+    padding
+}
+
+#[derive(Clone)]
+pub struct MerkleDamgard<C: Compress> {
+    c: C,
+    state: C::State,
+    buffer: Vec<u8>,
+    // number of bytes fed into the hasher, including the injected prefix
+    len: u64,
+    // number of bytes of the final state kept in the digest
+    output_size: Option<usize>,
+}
+
+impl<C: Compress> MerkleDamgard<C> {
+    pub fn new(c: C) -> Self {
+        let iv = c.iv();
+        Self::with_state(c, iv, 0)
+    }
+    // resume hashing from an intermediate state, `len` is the number of bytes
+    // (padded to a block boundary) that produced `state`
+    pub fn with_state(c: C, state: C::State, len: u64) -> Self {
+        assert!(len.is_multiple_of(C::BLOCK_SIZE as u64));
+        Self {
+            c,
+            state,
+            buffer: vec![],
+            len,
+            output_size: None,
+        }
+    }
+    // keep only the first `n` bytes of the digest
+    pub fn truncate(mut self, n: usize) -> Self {
+        self.output_size = Some(n);
+        self
+    }
+    pub fn state(&self) -> C::State {
+        self.state
+    }
+    pub fn padding(len: usize) -> Vec<u8> {
+        md_padding(len, C::BLOCK_SIZE, C::LENGTH_SIZE, C::BIG_ENDIAN)
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        self.len += data.len() as u64;
+        self.buffer.extend(data);
+        let full = self.buffer.len() - self.buffer.len() % C::BLOCK_SIZE;
+        for chunk in self.buffer[..full].chunks(C::BLOCK_SIZE) {
+            self.c.compress(&mut self.state, chunk);
+        }
+        self.buffer.drain(..full);
+    }
+    pub fn finalize(mut self) -> Vec<u8> {
+        let padding = Self::padding(self.len as usize);
+        self.update(&padding);
+        assert!(self.buffer.is_empty());
+        let mut digest = self.c.to_bytes(&self.state);
+        if let Some(n) = self.output_size {
+            digest.truncate(n);
+        }
+        digest
+    }
+}
+
+// The toy hash of set 7: each block is encrypted with AES-128 keyed by the
+// state (padded with zeros) and the first `N` bytes of the ciphertext are the
+// next state. With 2 to 4 byte states, collisions take a fraction of a second.
+#[derive(Clone, Copy, Debug)]
+pub struct AesHash<const N: usize> {
+    pub iv: [u8; N],
+}
+
+impl<const N: usize> AesHash<N> {
+    pub fn new(iv: [u8; N]) -> Self {
+        assert!(N <= 16);
+        Self { iv }
+    }
+}
+
+impl<const N: usize> Default for AesHash<N> {
+    fn default() -> Self {
+        Self::new([0; N])
+    }
+}
+
+impl<const N: usize> Compress for AesHash<N> {
+    type State = [u8; N];
+    const BLOCK_SIZE: usize = 16;
+    const BIG_ENDIAN: bool = true;
+
+    fn iv(&self) -> [u8; N] {
+        self.iv
+    }
+    fn compress(&self, state: &mut [u8; N], block: &[u8]) {
+        let mut key = [0; 16];
+        key[..N].copy_from_slice(state);
+        let out = cipher(block, &key_expansion(&key));
+        state.copy_from_slice(&out[..N]);
+    }
+    fn to_bytes(&self, state: &[u8; N]) -> Vec<u8> {
+        state.to_vec()
+    }
+    fn state_from_bytes(&self, bytes: &[u8]) -> [u8; N] {
+        bytes.try_into().unwrap()
+    }
+}
+
+pub type ToyHash<const N: usize> = MerkleDamgard<AesHash<N>>;
+
+pub fn toy_hash<const N: usize>(message: &[u8]) -> Vec<u8> {
+    let mut hasher = ToyHash::new(AesHash::<N>::default());
+    hasher.update(message);
+    hasher.finalize()
+}
+
+pub fn digest<C: Compress>(c: &C, message: &[u8]) -> Vec<u8> {
+    let mut hasher = MerkleDamgard::new(c.clone());
+    hasher.update(message);
+    hasher.finalize()
+}
+
+// two blocks taking `s1` and `s2` to the same state, found by compressing the
+// same random blocks from both until the birthday bound catches up
+pub fn collide_states<C>(c: &C, s1: C::State, s2: C::State) -> (Vec<u8>, Vec<u8>, C::State)
+where
+    C: Compress,
+    C::State: Hash + Eq,
+{
+    collide_states_with(&mut os(), c, s1, s2)
+}
+
+pub fn collide_states_with<C>(
+    rng: &mut impl RandomSource,
+    c: &C,
+    s1: C::State,
+    s2: C::State,
+) -> (Vec<u8>, Vec<u8>, C::State)
+where
+    C: Compress,
+    C::State: Hash + Eq,
+{
+    let (mut left, mut right) = (HashMap::new(), HashMap::new());
+    loop {
+        let block = rng.bytes(C::BLOCK_SIZE);
+        let (mut t1, mut t2) = (s1, s2);
+        c.compress(&mut t1, &block);
+        c.compress(&mut t2, &block);
+        left.insert(t1, block.clone());
+        right.insert(t2, block);
+        for t in [t1, t2] {
+            if let (Some(b1), Some(b2)) = (left.get(&t), right.get(&t)) {
+                return (b1.clone(), b2.clone(), t);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_md_padding() {
+        for len in [0, 1, 55, 56, 63, 64, 100] {
+            assert_eq!((len + md_padding(len, 64, 8, true).len()) % 64, 0);
+            assert_eq!((len + md_padding(len, 16, 8, true).len()) % 16, 0);
+            assert_eq!((len + md_padding(len, 128, 16, true).len()) % 128, 0);
+        }
+        assert_eq!(
+            md_padding(3, 16, 8, true),
+            [0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 24]
+        );
+        assert_eq!(
+            md_padding(3, 16, 8, false),
+            [0x80, 0, 0, 0, 0, 24, 0, 0, 0, 0, 0, 0, 0]
+        );
+        // a 16 byte length leaves 12 bytes of padding before it
+        let padding = md_padding(100, 128, 16, true);
+        assert_eq!(padding.len(), 28);
+        assert_eq!(padding[12..], (800u128).to_be_bytes());
+    }
+
+    #[test]
+    fn test_toy_hash() {
+        let msg = b"YELLOW SUBMARINE and some more blocks of text";
+        assert_eq!(toy_hash::<2>(msg).len(), 2);
+        assert_eq!(toy_hash::<4>(msg).len(), 4);
+        assert_ne!(toy_hash::<4>(msg), toy_hash::<4>(b"YELLOW SUBMARINE"));
+
+        let mut hasher = ToyHash::new(AesHash::<4>::default());
+        for chunk in msg.chunks(7) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), toy_hash::<4>(msg));
+
+        // the state after the first block is where a second hasher resumes
+        let mut hasher = ToyHash::new(AesHash::<3>::default());
+        hasher.update(&msg[..16]);
+        let mut resumed = ToyHash::with_state(AesHash::<3>::default(), hasher.state(), 16);
+        resumed.update(&msg[16..]);
+        assert_eq!(resumed.finalize(), toy_hash::<3>(msg));
+
+        let hasher = ToyHash::new(AesHash::<4>::default()).truncate(2);
+        assert_eq!(hasher.finalize(), toy_hash::<4>(b"")[..2]);
+    }
+}
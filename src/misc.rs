@@ -1,14 +1,37 @@
 #![allow(unused)]
 use std::{collections::HashMap, fs::File, io::Read};
 
-use crate::block_cipher_mode::{decrypt_in_ecb_mode, encrypt_in_ecb_mode};
+use rand_core::RngCore;
+
+use crate::{
+    block_cipher_mode::{decrypt_in_ecb_mode, encrypt_in_ecb_mode},
+    random::{os, RandomSource},
+};
+
+// `len` bytes from any generator, a seeded MT19937 makes them predictable
+pub fn rand_vec_from<R: RngCore>(rng: &mut R, len: usize) -> Vec<u8> {
+    let mut res = vec![0; len];
+    rng.fill_bytes(&mut res);
+    res
+}
 
 pub fn rand_vec(len: usize) -> Vec<u8> {
-    let mut res = vec![];
-    for i in 0..len {
-        res.push(rand::random::<u8>());
+    rand_vec_from(&mut os(), len)
+}
+
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|n| format!("{:02x}", n)).collect()
+}
+
+// None unless `s` is an even number of hex digits
+pub fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
     }
-    res
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
 }
 
 pub fn get_eng_histogram() -> HashMap<u8, f64> {
@@ -50,11 +73,10 @@ pub fn profile_for(email: &str) -> Profile {
 
 impl ProfileCrypt {
     pub fn new() -> Self {
-        let mut key = vec![];
-        for _ in 0..16 {
-            key.push(rand::random::<u8>());
-        }
-        Self { key }
+        Self::with_rng(&mut os())
+    }
+    pub fn with_rng(rng: &mut impl RandomSource) -> Self {
+        Self { key: rng.bytes(16) }
     }
     pub fn decrypt_profile(&self, input: &[u8]) -> Profile {
         let data = decrypt_in_ecb_mode(input, &self.key);
@@ -90,8 +112,28 @@ impl Profile {
 
 #[cfg(test)]
 mod tests {
+    use rand::seq::SliceRandom;
+    use rand_core::SeedableRng;
+
+    use crate::mt19937::MT19937;
+
     use super::*;
 
+    #[test]
+    fn test_rand_vec_from() {
+        let mut rng = MT19937::seed_from_u64(5489);
+        let key = rand_vec_from(&mut rng, 16);
+        assert_eq!(key[..4], 3499211612u32.to_le_bytes());
+        // the same seed gives the same key and the same shuffle
+        let mut cards = (0..52).collect::<Vec<_>>();
+        cards.shuffle(&mut rng);
+        let mut rng = MT19937::seed_from_u64(5489);
+        assert_eq!(rand_vec_from(&mut rng, 16), key);
+        let mut again = (0..52).collect::<Vec<_>>();
+        again.shuffle(&mut rng);
+        assert_eq!(cards, again);
+    }
+
     #[test]
     fn test_cur_and_paste() {
         let crypt = ProfileCrypt::new();
@@ -1,7 +1,13 @@
 #![allow(unused)]
-const N: usize = 624;
+use rand_core::{impls, RngCore, SeedableRng};
+pub const N: usize = 624;
+const MATRIX_A: u32 = 0x9908_b0df;
+const MULT: u32 = 1812433253;
+
+#[derive(Clone)]
 pub struct MT19937 {
     mt: [u32; N],
+    // the next word to temper, N when the state has to be twisted first
     mti: usize,
 }
 
@@ -10,41 +16,302 @@ impl MT19937 {
         let mut mt = [0u32; N];
         mt[0] = seed;
         for i in 1..N {
-            mt[i] = 1812433253u32.wrapping_mul(mt[i - 1] ^ (mt[i - 1] >> 30)) + i as u32;
+            mt[i] = MULT
+                .wrapping_mul(mt[i - 1] ^ (mt[i - 1] >> 30))
+                .wrapping_add(i as u32);
+        }
+        Self { mt, mti: N }
+    }
+
+    // init_by_array from the reference implementation, an empty key is [0]
+    pub fn new_by_array(key: &[u32]) -> Self {
+        let key = if key.is_empty() { &[0][..] } else { key };
+        let mut mt = Self::new(19650218).mt;
+        let mut i = 1;
+        for k in 0..N.max(key.len()) {
+            let j = k % key.len();
+            mt[i] = (mt[i] ^ (mt[i - 1] ^ (mt[i - 1] >> 30)).wrapping_mul(1664525))
+                .wrapping_add(key[j])
+                .wrapping_add(j as u32);
+            i += 1;
+            if i == N {
+                mt[0] = mt[N - 1];
+                i = 1;
+            }
+        }
+        for _ in 1..N {
+            mt[i] = (mt[i] ^ (mt[i - 1] ^ (mt[i - 1] >> 30)).wrapping_mul(1566083941))
+                .wrapping_sub(i as u32);
+            i += 1;
+            if i == N {
+                mt[0] = mt[N - 1];
+                i = 1;
+            }
+        }
+        mt[0] = 0x8000_0000;
+        Self { mt, mti: N }
+    }
+
+    // C++ `std::mt19937 rng(seq)` for a `std::seed_seq seq(key)`
+    pub fn new_by_seed_seq(key: &[u32]) -> Self {
+        let mut mt: [u32; N] = seed_seq(key, N).try_into().unwrap();
+        // the state can't be all zero
+        if mt[0] & 0x8000_0000 == 0 && mt[1..].iter().all(|v| *v == 0) {
+            mt[0] = 0x8000_0000;
+        }
+        Self { mt, mti: N }
+    }
+
+    // `random.seed(seed)` in Python, the words of the seed are the key
+    pub fn new_python(seed: u64) -> Self {
+        if seed >> 32 == 0 {
+            Self::new_by_array(&[seed as u32])
+        } else {
+            Self::new_by_array(&[seed as u32, (seed >> 32) as u32])
         }
-        Self { mt, mti: 0 }
     }
 
     pub fn new_with_mt(mt: Vec<u32>) -> Self {
         Self {
             mt: mt.try_into().unwrap(),
-            mti: 0,
+            mti: N,
         }
     }
 
+    // a generator whose next outputs are the tempered words of `mt`, without
+    // twisting it first
+    pub fn with_twisted_state(mt: [u32; N]) -> Self {
+        Self { mt, mti: 0 }
+    }
+
     pub fn gen_u32(&mut self) -> u32 {
-        if self.mti == 0 {
+        if self.mti == N {
             self.twist();
+            self.mti = 0;
         }
 
-        let mut y = self.mt[self.mti];
-        y ^= y >> 11;
-        y ^= (y << 7) & 2636928640;
-        y ^= (y << 15) & 4022730752;
-        y ^= y >> 18;
-        self.mti = (self.mti + 1) % 624;
+        let y = temper(self.mt[self.mti]);
+        self.mti += 1;
         y
     }
 
+    // Python's `random()`, NumPy's `random_sample()`: 53 random bits from two
+    // outputs
+    pub fn gen_f64(&mut self) -> f64 {
+        let a = (self.gen_u32() >> 5) as u64;
+        let b = (self.gen_u32() >> 6) as u64;
+        ((a << 26) + b) as f64 / (1u64 << 53) as f64
+    }
+
+    // Python's `getrandbits(k)` for k <= 64, the first output is the low word
+    pub fn getrandbits(&mut self, k: u32) -> u64 {
+        assert!((1..=64).contains(&k));
+        if k <= 32 {
+            return (self.gen_u32() >> (32 - k)) as u64;
+        }
+        let low = self.gen_u32() as u64;
+        let high = (self.gen_u32() >> (64 - k)) as u64;
+        low | high << 32
+    }
+
+    // Python's `randrange(n)`: k bit values are drawn until one is below n
+    pub fn randrange(&mut self, n: u64) -> u64 {
+        assert!(n > 0);
+        let k = 64 - n.leading_zeros();
+        loop {
+            let r = self.getrandbits(k);
+            if r < n {
+                return r;
+            }
+        }
+    }
+
     fn twist(&mut self) {
         for i in 0..N {
             let y = (self.mt[i] & 0x8000_0000) + (self.mt[(i + 1) % N] & 0x7fff_ffff);
             self.mt[i] = (y >> 1) ^ self.mt[(i + 397) % N];
             if y % 2 != 0 {
-                self.mt[i] = self.mt[i] ^ 0x9908_b0df;
+                self.mt[i] ^= MATRIX_A;
+            }
+        }
+    }
+
+    // Undo one twist. Word i of the old state gets its top bit from new word i
+    // and its low bits from new word i - 1, the first word's low bits can't be
+    // recovered when the old state is the one right after seeding.
+    pub fn untwist(&mut self) {
+        for i in (0..N).rev() {
+            let y = untwist_word(self.mt[i], self.mt[(i + 397) % N]);
+            let prev = untwist_word(self.mt[(i + N - 1) % N], self.mt[(i + 396) % N]);
+            self.mt[i] = (y & 0x8000_0000) | (prev & 0x7fff_ffff);
+        }
+    }
+
+    // step back `n` outputs, so that they come out again
+    pub fn rewind(&mut self, n: usize) {
+        for _ in 0..n {
+            if self.mti == 0 {
+                self.untwist();
+                self.mti = N;
             }
+            self.mti -= 1;
         }
     }
+
+    // The seed of a generator rewound to its first output. Numbering the
+    // seeded words 0..624 and the outputs from 624 on, the twist makes word
+    // k + 624 from words k, k + 1 and k + 397. For k >= 227 both k + 624 and
+    // k + 397 are among the first 624 outputs, so those alone give the seeded
+    // words 228..624. The seeding is then run backwards.
+    pub fn seed(&self) -> Option<u32> {
+        let mut rng = self.clone();
+        let x = (0..N).map(|_| untemper(rng.gen_u32())).collect::<Vec<_>>();
+        let mut mt = [0; N];
+        for k in 227..N {
+            let y = untwist_word(x[k], x[k + 397 - N]);
+            mt[k] |= y & 0x8000_0000;
+            if k + 1 < N {
+                mt[k + 1] |= y & 0x7fff_ffff;
+            }
+        }
+        let mut seed = mt[228];
+        for i in (1..=228).rev() {
+            seed = unseed_step(seed, i as u32);
+        }
+        let mut check = MT19937::new(seed);
+        x.iter()
+            .all(|v| untemper(check.gen_u32()) == *v)
+            .then_some(seed)
+    }
+}
+
+// `std::seed_seq::generate` filling `n` words
+pub fn seed_seq(key: &[u32], n: usize) -> Vec<u32> {
+    let mut v = vec![0x8b8b_8b8bu32; n];
+    let s = key.len();
+    let t = match n {
+        623.. => 11,
+        68.. => 7,
+        39.. => 5,
+        7.. => 3,
+        _ => (n - 1) / 2,
+    };
+    let p = (n - t) / 2;
+    let q = p + t;
+    let m = (s + 1).max(n);
+    let mix = |x: u32| x ^ (x >> 27);
+    for k in 0..m {
+        let r1 = 1664525u32.wrapping_mul(mix(v[k % n] ^ v[(k + p) % n] ^ v[(k + n - 1) % n]));
+        let r2 = r1.wrapping_add(match k {
+            0 => s as u32,
+            _ if k <= s => ((k % n) as u32).wrapping_add(key[k - 1]),
+            _ => (k % n) as u32,
+        });
+        v[(k + p) % n] = v[(k + p) % n].wrapping_add(r1);
+        v[(k + q) % n] = v[(k + q) % n].wrapping_add(r2);
+        v[k % n] = r2;
+    }
+    for k in m..m + n {
+        let r3 = 1566083941u32.wrapping_mul(mix(v[k % n]
+            .wrapping_add(v[(k + p) % n])
+            .wrapping_add(v[(k + n - 1) % n])));
+        let r4 = r3.wrapping_sub((k % n) as u32);
+        v[(k + p) % n] ^= r3;
+        v[(k + q) % n] ^= r4;
+        v[k % n] = r4;
+    }
+    v
+}
+
+// plugs into `rand`, `fill_bytes` writes every output little endian like the
+// reference `genrand_int32` dumped to memory on x86
+impl RngCore for MT19937 {
+    fn next_u32(&mut self) -> u32 {
+        self.gen_u32()
+    }
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_u32(self)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for MT19937 {
+    type Seed = [u8; 4];
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::new(u32::from_le_bytes(seed))
+    }
+    // the same generator as `MT19937::new(state as u32)`, so weak seeds stay weak
+    fn seed_from_u64(state: u64) -> Self {
+        Self::new(state as u32)
+    }
+}
+
+// the combined word y = top bit of x[k] | low bits of x[k + 1] that twisted into
+// `new` = x[k + 624], given `other` = x[k + 397]
+fn untwist_word(new: u32, other: u32) -> u32 {
+    let mut v = new ^ other;
+    // A has its top bit set and y >> 1 doesn't, so it tells whether y was odd
+    let odd = v >> 31;
+    if odd == 1 {
+        v ^= MATRIX_A;
+    }
+    (v << 1) | odd
+}
+
+// the inverse of a modulo 2^32, a odd
+fn inverse(a: u32) -> u32 {
+    let mut x = a;
+    // every step doubles the number of correct low bits
+    for _ in 0..5 {
+        x = x.wrapping_mul(2u32.wrapping_sub(a.wrapping_mul(x)));
+    }
+    x
+}
+
+// x[i - 1] from x[i] = MULT * (x[i - 1] ^ (x[i - 1] >> 30)) + i
+fn unseed_step(x: u32, i: u32) -> u32 {
+    let y = x.wrapping_sub(i).wrapping_mul(inverse(MULT));
+    y ^ (y >> 30)
+}
+
+// the seed of the state right after seeding, if `mt` is one
+pub fn seed_from_state(mt: &[u32; N]) -> Option<u32> {
+    let seed = unseed_step(mt[1], 1);
+    (MT19937::new(seed).mt[1..] == mt[1..]).then_some(seed)
+}
+
+// Output `index` < N - 397 of `MT19937::new(seed)` only needs the seeded words
+// up to index + 397, which saves most of the seeding and the whole twist for
+// seeds that don't match.
+pub fn early_output(seed: u32, index: usize) -> u32 {
+    assert!(index < N - 397);
+    let mut mt = [0u32; N];
+    mt[0] = seed;
+    for i in 1..=index + 397 {
+        mt[i] = MULT
+            .wrapping_mul(mt[i - 1] ^ (mt[i - 1] >> 30))
+            .wrapping_add(i as u32);
+    }
+    let y = (mt[index] & 0x8000_0000) | (mt[index + 1] & 0x7fff_ffff);
+    let mut next = (y >> 1) ^ mt[index + 397];
+    if y & 1 == 1 {
+        next ^= MATRIX_A;
+    }
+    temper(next)
+}
+
+pub fn temper(mut y: u32) -> u32 {
+    y ^= y >> 11;
+    y ^= (y << 7) & 2636928640;
+    y ^= (y << 15) & 4022730752;
+    y ^= y >> 18;
+    y
 }
 
 pub fn untemper(val: u32) -> u32 {
@@ -170,4 +437,116 @@ mod tests {
         let res = untemper(y);
         assert_eq!(res, origin);
     }
+
+    #[test]
+    fn test_untwist() {
+        for seed in [0, 5489, 0x9e37_79b9, u32::MAX] {
+            let mut rng = MT19937::new(seed);
+            let outputs = (0..2000).map(|_| rng.gen_u32()).collect::<Vec<_>>();
+            let mut twisted = rng.clone();
+            twisted.twist();
+            twisted.untwist();
+            assert_eq!(twisted.mt, rng.mt, "seed {seed}");
+
+            // back to the seeded state, whose first word isn't needed
+            rng.rewind(2000);
+            for v in &outputs {
+                assert_eq!(rng.gen_u32(), *v, "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_seed() {
+        assert_eq!(inverse(MULT).wrapping_mul(MULT), 1);
+        for seed in [0, 5489, 0x9e37_79b9, u32::MAX] {
+            assert_eq!(
+                seed_from_state(&MT19937::new(seed).mt),
+                Some(seed),
+                "seed {seed}"
+            );
+            assert_eq!(MT19937::new(seed).seed(), Some(seed), "seed {seed}");
+
+            let mut rng = MT19937::new(seed);
+            rng.gen_u32();
+            assert_eq!(seed_from_state(&rng.mt), None, "seed {seed}");
+            assert_eq!(rng.seed(), None, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn test_early_output() {
+        for seed in [0, 1234, u32::MAX] {
+            let mut rng = MT19937::new(seed);
+            for index in 0..N - 397 {
+                assert_eq!(early_output(seed, index), rng.gen_u32());
+            }
+        }
+    }
+
+    #[test]
+    fn test_new_wrapping() {
+        // the seeding used to overflow in debug builds
+        let mut rng = MT19937::new(u32::MAX);
+        rng.gen_u32();
+        let mut rng = MT19937::new(5489);
+        for _ in 0..9999 {
+            rng.gen_u32();
+        }
+        assert_eq!(rng.gen_u32(), 4123659995);
+    }
+
+    #[test]
+    fn test_new_by_array() {
+        // mt19937ar.out
+        let mut rng = MT19937::new_by_array(&[0x123, 0x234, 0x345, 0x456]);
+        let outputs = (0..3).map(|_| rng.gen_u32()).collect::<Vec<_>>();
+        assert_eq!(outputs, [1067595299, 955945823, 477289528]);
+
+        // std::seed_seq seq{1, 2, 3}; std::mt19937 rng(seq);
+        let mut rng = MT19937::new_by_seed_seq(&[1, 2, 3]);
+        let outputs = (0..3).map(|_| rng.gen_u32()).collect::<Vec<_>>();
+        assert_eq!(outputs, [1710881851, 703781052, 629188492]);
+    }
+
+    #[test]
+    fn test_python() {
+        // random.Random(42)
+        let mut rng = MT19937::new_python(42);
+        assert_eq!(rng.gen_f64(), 0.6394267984578837);
+        assert_eq!(rng.gen_f64(), 0.025010755222666936);
+
+        let mut rng = MT19937::new_python(42);
+        assert_eq!(rng.getrandbits(5), 20);
+        assert_eq!(rng.getrandbits(32), 478163327);
+        assert_eq!(rng.getrandbits(48), 208726928071377);
+        assert_eq!(rng.getrandbits(64), 4517457392071889495);
+
+        let mut rng = MT19937::new_python(42);
+        let outputs = (0..5).map(|_| rng.randrange(1000)).collect::<Vec<_>>();
+        assert_eq!(outputs, [654, 114, 25, 759, 281]);
+        assert_eq!(10 + rng.randrange(10), 13);
+
+        assert_eq!(MT19937::new_python(0).gen_u32(), 3626764237);
+        assert_eq!(MT19937::new_python((1 << 40) + 7).gen_u32(), 2635837658);
+    }
+
+    #[test]
+    fn test_rng_core() {
+        let mut rng = MT19937::seed_from_u64(5489);
+        let mut reference = MT19937::new(5489);
+        let mut bytes = [0; 7];
+        rng.fill_bytes(&mut bytes);
+        let a = reference.gen_u32().to_le_bytes();
+        let b = reference.gen_u32().to_le_bytes();
+        assert_eq!(bytes[..4], a);
+        assert_eq!(bytes[4..], b[..3]);
+        // a partly used output is dropped
+        assert_eq!(rng.next_u32(), reference.gen_u32());
+        let (lo, hi) = (reference.gen_u32() as u64, reference.gen_u32() as u64);
+        assert_eq!(rng.next_u64(), lo | hi << 32);
+
+        let mut rng = MT19937::from_seed(1234u32.to_le_bytes());
+        assert_eq!(rng.gen_u32(), MT19937::new(1234).gen_u32());
+    }
 }
@@ -0,0 +1,186 @@
+#![allow(unused)]
+use rand_core::{impls, RngCore, SeedableRng};
+
+use crate::mt19937::seed_seq;
+
+pub const N: usize = 312;
+const M: usize = 156;
+const MATRIX_A: u64 = 0xb502_6f5a_a966_19e9;
+const UPPER_MASK: u64 = 0xffff_ffff_8000_0000;
+const LOWER_MASK: u64 = 0x7fff_ffff;
+
+// the 64-bit Mersenne Twister, `std::mt19937_64` in C++
+#[derive(Clone)]
+pub struct MT19937_64 {
+    mt: [u64; N],
+    // the next word to temper, N when the state has to be twisted first
+    mti: usize,
+}
+
+impl MT19937_64 {
+    pub fn new(seed: u64) -> Self {
+        let mut mt = [0u64; N];
+        mt[0] = seed;
+        for i in 1..N {
+            mt[i] = 6364136223846793005u64
+                .wrapping_mul(mt[i - 1] ^ (mt[i - 1] >> 62))
+                .wrapping_add(i as u64);
+        }
+        Self { mt, mti: N }
+    }
+
+    // init_by_array64 from the reference implementation, an empty key is [0]
+    pub fn new_by_array(key: &[u64]) -> Self {
+        let key = if key.is_empty() { &[0][..] } else { key };
+        let mut mt = Self::new(19650218).mt;
+        let mut i = 1;
+        for k in 0..N.max(key.len()) {
+            let j = k % key.len();
+            mt[i] = (mt[i] ^ (mt[i - 1] ^ (mt[i - 1] >> 62)).wrapping_mul(3935559000370003845))
+                .wrapping_add(key[j])
+                .wrapping_add(j as u64);
+            i += 1;
+            if i == N {
+                mt[0] = mt[N - 1];
+                i = 1;
+            }
+        }
+        for _ in 1..N {
+            mt[i] = (mt[i] ^ (mt[i - 1] ^ (mt[i - 1] >> 62)).wrapping_mul(2862933555777941757))
+                .wrapping_sub(i as u64);
+            i += 1;
+            if i == N {
+                mt[0] = mt[N - 1];
+                i = 1;
+            }
+        }
+        mt[0] = 1 << 63;
+        Self { mt, mti: N }
+    }
+
+    // C++ `std::mt19937_64 rng(seq)` for a `std::seed_seq seq(key)`, every
+    // word takes two 32-bit values, low one first
+    pub fn new_by_seed_seq(key: &[u32]) -> Self {
+        let v = seed_seq(key, 2 * N);
+        let mut mt: [u64; N] =
+            std::array::from_fn(|i| v[2 * i] as u64 | (v[2 * i + 1] as u64) << 32);
+        if mt[0] & UPPER_MASK == 0 && mt[1..].iter().all(|v| *v == 0) {
+            mt[0] = 1 << 63;
+        }
+        Self { mt, mti: N }
+    }
+
+    pub fn gen_u64(&mut self) -> u64 {
+        if self.mti == N {
+            self.twist();
+            self.mti = 0;
+        }
+
+        let mut y = self.mt[self.mti];
+        y ^= (y >> 29) & 0x5555_5555_5555_5555;
+        y ^= (y << 17) & 0x71d6_7fff_eda6_0000;
+        y ^= (y << 37) & 0xfff7_eee0_0000_0000;
+        y ^= y >> 43;
+        self.mti += 1;
+        y
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N {
+            let y = (self.mt[i] & UPPER_MASK) | (self.mt[(i + 1) % N] & LOWER_MASK);
+            self.mt[i] = (y >> 1) ^ self.mt[(i + M) % N];
+            if y & 1 == 1 {
+                self.mt[i] ^= MATRIX_A;
+            }
+        }
+    }
+}
+
+// `next_u32` is the low half of an output, `fill_bytes` writes every output
+// little endian
+impl RngCore for MT19937_64 {
+    fn next_u32(&mut self) -> u32 {
+        self.gen_u64() as u32
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.gen_u64()
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for MT19937_64 {
+    type Seed = [u8; 8];
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::new(u64::from_le_bytes(seed))
+    }
+    fn seed_from_u64(state: u64) -> Self {
+        Self::new(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(rng: &mut MT19937_64, n: usize) -> Vec<u64> {
+        (0..n).map(|_| rng.gen_u64()).collect()
+    }
+
+    #[test]
+    fn test_mt19937_64() {
+        // the 10000th output of a default constructed std::mt19937_64
+        let mut rng = MT19937_64::new(5489);
+        assert_eq!(outputs(&mut rng, 10000)[9999], 9981545732273789042);
+
+        let mut rng = MT19937_64::new(42);
+        assert_eq!(
+            outputs(&mut rng, 3),
+            [
+                13930160852258120406,
+                11788048577503494824,
+                13874630024467741450
+            ]
+        );
+    }
+
+    #[test]
+    fn test_seeding() {
+        // mt19937-64.out
+        let mut rng = MT19937_64::new_by_array(&[0x12345, 0x23456, 0x34567, 0x45678]);
+        assert_eq!(
+            outputs(&mut rng, 3),
+            [
+                7266447313870364031,
+                4946485549665804864,
+                16945909448695747420
+            ]
+        );
+
+        // std::seed_seq seq{1, 2, 3}; std::mt19937_64 rng(seq);
+        let mut rng = MT19937_64::new_by_seed_seq(&[1, 2, 3]);
+        assert_eq!(
+            outputs(&mut rng, 3),
+            [
+                1831209241179374162,
+                4398843623863442686,
+                2280222209083243558
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rng_core() {
+        let mut rng = MT19937_64::seed_from_u64(42);
+        let mut bytes = [0; 12];
+        rng.fill_bytes(&mut bytes);
+        assert_eq!(bytes[..8], 13930160852258120406u64.to_le_bytes());
+        assert_eq!(bytes[8..], 11788048577503494824u64.to_le_bytes()[..4]);
+        assert_eq!(rng.next_u32(), 13874630024467741450u64 as u32);
+    }
+}
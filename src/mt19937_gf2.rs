@@ -0,0 +1,263 @@
+#![allow(unused)]
+use crate::mt19937::{MT19937, N};
+
+// The unknowns are the 19968 bits of the state right after the first twist,
+// the words the first 624 outputs are tempered from. Bit b of word k is
+// variable 32 * k + b. Every bit of the generator is a xor of some of them.
+const VARS: usize = N * 32;
+const LEN: usize = VARS / 64;
+
+// the low 31 bits of the first word only show up in the first output, the
+// other 19937 bits are all that matters for the ones after it
+const HIDDEN_BITS: usize = 31;
+
+type Bits = Vec<u64>;
+// the 32 bits of a word
+type Word = Vec<Bits>;
+
+fn xor(a: &mut Bits, b: &Bits) {
+    for (x, y) in a.iter_mut().zip(b) {
+        *x ^= y;
+    }
+}
+
+fn variable(i: usize) -> Bits {
+    let mut v = vec![0; LEN];
+    v[i / 64] |= 1 << (i % 64);
+    v
+}
+
+// x ^ (x >> s) and x ^ ((x << s) & mask), bit by bit
+fn xor_shift_right(x: &Word, s: usize) -> Word {
+    let mut res = x.clone();
+    for i in 0..32 - s {
+        xor(&mut res[i], &x[i + s]);
+    }
+    res
+}
+
+fn xor_shift_left(x: &Word, s: usize, mask: u32) -> Word {
+    let mut res = x.clone();
+    for i in s..32 {
+        if mask >> i & 1 == 1 {
+            xor(&mut res[i], &x[i - s]);
+        }
+    }
+    res
+}
+
+fn temper(y: &Word) -> Word {
+    let y = xor_shift_right(y, 11);
+    let y = xor_shift_left(&y, 7, 2636928640);
+    let y = xor_shift_left(&y, 15, 4022730752);
+    xor_shift_right(&y, 18)
+}
+
+// the same twist as `MT19937`, words are updated in place so the last ones
+// depend on the new first ones
+fn twist(mt: &mut [Word]) {
+    for i in 0..N {
+        // the top bit of mt[i] and the low 31 bits of mt[i + 1]
+        let mut y = mt[(i + 1) % N].clone();
+        y[31] = mt[i][31].clone();
+        let mut next = mt[(i + 397) % N].clone();
+        // y >> 1
+        for (n, v) in next.iter_mut().zip(&y[1..]) {
+            xor(n, v);
+        }
+        // the multiplication by the matrix A xors 0x9908b0df when y is odd
+        for (b, n) in next.iter_mut().enumerate() {
+            if 0x9908_b0df_u32 >> b & 1 == 1 {
+                xor(n, &y[0]);
+            }
+        }
+        mt[i] = next;
+    }
+}
+
+// Known bits of one output: the output at `index`, counted from the first one
+// after seeding, has `value` in the bits set in `mask`. The top 8 bits are
+// `mask: 0xff00_0000`, `rand() % 2^k` leaks `mask: (1 << k) - 1`.
+#[derive(Clone, Copy, Debug)]
+pub struct Leak {
+    pub index: usize,
+    pub mask: u32,
+    pub value: u32,
+}
+
+// The rows of an echelon form, `rows[c]` is the one whose lowest bit is c.
+// Variable c is known once it and every variable after it have a row.
+struct System {
+    rows: Vec<Option<(Bits, bool)>>,
+}
+
+impl System {
+    fn new() -> Self {
+        Self {
+            rows: vec![None; VARS],
+        }
+    }
+
+    // false if the equation contradicts the ones before
+    fn add(&mut self, mut row: Bits, mut rhs: bool) -> bool {
+        let mut w = 0;
+        while w < LEN {
+            if row[w] == 0 {
+                w += 1;
+                continue;
+            }
+            let c = w * 64 + row[w].trailing_zeros() as usize;
+            match &self.rows[c] {
+                Some((other, other_rhs)) => {
+                    // the other row has no bits below c
+                    for (x, y) in row[w..].iter_mut().zip(&other[w..]) {
+                        *x ^= y;
+                    }
+                    rhs ^= other_rhs;
+                }
+                None => {
+                    self.rows[c] = Some((row, rhs));
+                    return true;
+                }
+            }
+        }
+        !rhs
+    }
+
+    // back substitution from the highest pivot, free variables are zero
+    fn solve(&self) -> Bits {
+        let mut x = vec![0; LEN];
+        for c in (0..VARS).rev() {
+            if let Some((row, rhs)) = &self.rows[c] {
+                let w = c / 64;
+                let parity = row[w..]
+                    .iter()
+                    .zip(&x[w..])
+                    .fold(0, |acc, (r, v)| acc ^ (r & v).count_ones())
+                    & 1;
+                if (parity == 1) != *rhs {
+                    x[w] |= 1 << (c % 64);
+                }
+            }
+        }
+        x
+    }
+}
+
+// The generator that produced the leaked bits, rewound to its first output,
+// or None when they contradict each other or don't pin the state down. The
+// bits of the first output that weren't leaked may be wrong. Leaked bits are
+// far from independent, the top byte of each output takes about 3600 outputs
+// rather than 19937 / 8.
+pub fn recover_state(leaks: &[Leak]) -> Option<MT19937> {
+    let mut leaks = leaks.to_vec();
+    leaks.sort_by_key(|l| l.index);
+
+    let mut state: Vec<Word> = (0..N)
+        .map(|k| (0..32).map(|b| variable(k * 32 + b)).collect())
+        .collect();
+    let mut block = 0;
+    let mut system = System::new();
+    for leak in leaks {
+        while leak.index / N > block {
+            twist(&mut state);
+            block += 1;
+        }
+        let out = temper(&state[leak.index % N]);
+        for (b, row) in out.into_iter().enumerate() {
+            if leak.mask >> b & 1 == 1 && !system.add(row, leak.value >> b & 1 == 1) {
+                return None;
+            }
+        }
+    }
+    if system.rows[HIDDEN_BITS..].iter().any(|r| r.is_none()) {
+        return None;
+    }
+
+    let x = system.solve();
+    let mt: [u32; N] = std::array::from_fn(|k| (x[k / 2] >> (k % 2 * 32)) as u32);
+    Some(MT19937::with_twisted_state(mt))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mt19937::untemper;
+
+    use super::*;
+
+    fn eval(expr: &Bits, x: &[u32; N]) -> bool {
+        (0..VARS)
+            .filter(|i| expr[i / 64] >> (i % 64) & 1 == 1)
+            .fold(false, |acc, i| acc ^ (x[i / 32] >> (i % 32) & 1 == 1))
+    }
+
+    #[test]
+    fn test_model() {
+        let mut state: Vec<Word> = (0..N)
+            .map(|k| (0..32).map(|b| variable(k * 32 + b)).collect())
+            .collect();
+        twist(&mut state);
+        for seed in [0, 5489, 0x9e37_79b9] {
+            let mut rng = MT19937::new(seed);
+            let outputs = (0..2 * N).map(|_| rng.gen_u32()).collect::<Vec<_>>();
+            let x: [u32; N] = std::array::from_fn(|k| untemper(outputs[k]));
+            for k in [0, 1, 226, 227, 400, 623] {
+                let out = temper(&state[k]);
+                let got = (0..32).fold(0, |acc, b| acc | (eval(&out[b], &x) as u32) << b);
+                assert_eq!(got, outputs[N + k], "seed {seed}, output {k}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_recover_full_outputs() {
+        let seed = 0x9e37_79b9;
+        let mut rng = MT19937::new(seed);
+        let leaks = (0..N)
+            .map(|index| Leak {
+                index,
+                mask: u32::MAX,
+                value: rng.gen_u32(),
+            })
+            .collect::<Vec<_>>();
+        let mut clone = recover_state(&leaks).unwrap();
+        for leak in &leaks {
+            assert_eq!(clone.gen_u32(), leak.value, "seed {seed}");
+        }
+        for _ in 0..1000 {
+            assert_eq!(clone.gen_u32(), rng.gen_u32(), "seed {seed}");
+        }
+
+        // too few bits, and bits that can't both be right
+        assert!(recover_state(&leaks[..600]).is_none());
+        let mut bad = leaks.clone();
+        bad.push(Leak {
+            index: 3,
+            mask: 1,
+            value: !leaks[3].value,
+        });
+        assert!(recover_state(&bad).is_none());
+    }
+
+    #[test]
+    fn test_recover_high_bits() {
+        let seed = 5489;
+        let mut rng = MT19937::new(seed);
+        // the top byte of every output, with a gap now and then
+        let leaks = (0..4000)
+            .map(|index| Leak {
+                index,
+                mask: 0xff00_0000,
+                value: rng.gen_u32() & 0xff00_0000,
+            })
+            .filter(|l| l.index % 10 != 3)
+            .collect::<Vec<_>>();
+        let mut clone = recover_state(&leaks).unwrap();
+        for _ in 0..4000 {
+            clone.gen_u32();
+        }
+        for _ in 0..1000 {
+            assert_eq!(clone.gen_u32(), rng.gen_u32(), "seed {seed}");
+        }
+    }
+}
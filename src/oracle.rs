@@ -7,6 +7,7 @@ use crate::{
     base64::base64_to_u8,
     block_cipher_mode::{encrypt_in_cbc_mode, encrypt_in_ecb_mode},
     pkcs::pkcs7unpadding,
+    random::{os, seeded, RandomSource},
 };
 
 pub struct Oracle<'a> {
@@ -17,13 +18,16 @@ pub struct Oracle<'a> {
 
 impl<'a> Oracle<'a> {
     pub fn new(suffix: &'a [u8], prefix: Option<&'a [u8]>) -> Self {
-        let mut key = vec![];
-        for _ in 0..16 {
-            key.push(rand::random::<u8>());
-        }
+        Self::with_rng(&mut os(), suffix, prefix)
+    }
+    pub fn with_rng(
+        rng: &mut impl RandomSource,
+        suffix: &'a [u8],
+        prefix: Option<&'a [u8]>,
+    ) -> Self {
         Self {
             suffix,
-            key,
+            key: rng.bytes(16),
             prefix,
         }
     }
@@ -40,28 +44,26 @@ impl<'a> Oracle<'a> {
     }
 }
 pub fn prefix_suffix_oracle(input: &[u8]) -> (&str, Vec<u8>) {
-    let mut key = vec![];
-    for _ in 0..16 {
-        key.push(rand::random::<u8>());
-    }
-    let mut res = vec![];
-    for _ in 0..rand::random::<u8>() % 6 + 5 {
-        res.push(rand::random::<u8>());
-    }
+    prefix_suffix_oracle_with(&mut os(), input)
+}
+
+pub fn prefix_suffix_oracle_with(
+    rng: &mut impl RandomSource,
+    input: &[u8],
+) -> (&'static str, Vec<u8>) {
+    let key = rng.bytes(16);
+    let len = rng.below(5..11);
+    let mut res = rng.bytes(len);
     res.extend(input);
-    for _ in 0..rand::random::<u8>() % 6 + 5 {
-        res.push(rand::random::<u8>());
-    }
+    let len = rng.below(5..11);
+    res.extend(rng.bytes(len));
     let mut mode = "CBC";
 
-    if rand::random() {
+    if rng.coin() {
         res = encrypt_in_ecb_mode(&res, &key);
         mode = "ECB"
     } else {
-        let mut iv = vec![];
-        for _ in 0..16 {
-            iv.push(rand::random::<u8>());
-        }
+        let iv = rng.bytes(16);
         res = encrypt_in_cbc_mode(&res, &key, &iv);
     }
     (mode, res)
@@ -196,6 +198,22 @@ mod tests {
             };
             assert_eq!(mode, expected_mode);
         }
+        // the same seed picks the same key, padding and mode
+        let mut rng = seeded(42);
+        let (mode, res) = prefix_suffix_oracle_with(&mut rng, input);
+        assert_eq!(
+            prefix_suffix_oracle_with(&mut seeded(42), input),
+            (mode, res)
+        );
+        for i in 0..200 {
+            let (expected_mode, res) = prefix_suffix_oracle_with(&mut rng, input);
+            let mode = if count_repetition_in(16, &res) > 0 {
+                "ECB"
+            } else {
+                "CBC"
+            };
+            assert_eq!(mode, expected_mode);
+        }
     }
 
     #[test]
@@ -217,12 +235,13 @@ aGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBq
 dXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUg
 YnkK";
         let suffix = base64_to_u8(suffix);
-        let mut prefix = vec![];
-        for _ in 0..rand::random::<u8>() % 100 {
-            prefix.push(rand::random::<u8>());
+        for seed in 0..2 {
+            let mut rng = seeded(seed);
+            let len = rng.below(0..100);
+            let prefix = rng.bytes(len);
+            let oracle = Oracle::with_rng(&mut rng, &suffix, Some(&prefix));
+            let res = decrypt_byte_by_byte(&oracle);
+            assert_eq!(res, suffix, "seed {seed}");
         }
-        let oracle = Oracle::new(&suffix, Some(&prefix));
-        let res = decrypt_byte_by_byte(&oracle);
-        assert_eq!(res, suffix);
     }
 }
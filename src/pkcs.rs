@@ -24,7 +24,8 @@ pub fn pkcs7unpadding(input: &[u8], k: u8) -> Vec<u8> {
 pub fn is_pkcs7_padding(input: &[u8], k: u8) -> bool {
     let l = input.len();
     let padding_size = *input.last().unwrap();
-    if l < padding_size as usize {
+    // a zero byte isn't padding, it would make any block valid
+    if padding_size == 0 || padding_size > k || l < padding_size as usize {
         return false;
     }
     input[l - padding_size as usize..l]
@@ -52,4 +53,15 @@ mod tests {
         let input = b"ICE ICE BABY\x01\x02\x03\x04";
         assert!(!is_pkcs7_padding(input, 16));
     }
+
+    #[test]
+    fn test_is_pkcs7_padding_bounds() {
+        // a zero byte, or padding longer than a block, is never valid
+        assert!(!is_pkcs7_padding(b"ICE ICE BABY\x01\x02\x03\x00", 16));
+        assert!(!is_pkcs7_padding(&[0; 16], 16));
+        assert!(!is_pkcs7_padding(&[17; 17], 16));
+        assert!(!is_pkcs7_padding(&[5; 5], 4));
+        assert!(is_pkcs7_padding(&[16; 16], 16));
+        assert!(is_pkcs7_padding(&[4; 4], 4));
+    }
 }
@@ -0,0 +1,347 @@
+#![allow(unused)]
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::birthday::truncate;
+
+// the plaintexts a table covers, numbered 0..size
+pub trait Space {
+    fn size(&self) -> u64;
+    fn get(&self, i: u64) -> Vec<u8>;
+}
+
+// zero padded decimal PINs of `digits` digits
+pub struct Pins {
+    pub digits: u32,
+}
+
+impl Space for Pins {
+    fn size(&self) -> u64 {
+        10u64.pow(self.digits)
+    }
+    fn get(&self, i: u64) -> Vec<u8> {
+        format!("{:0width$}", i, width = self.digits as usize).into_bytes()
+    }
+}
+
+// every string of `len` characters from `chars`
+pub struct Charset {
+    pub chars: Vec<u8>,
+    pub len: u32,
+}
+
+impl Space for Charset {
+    fn size(&self) -> u64 {
+        (self.chars.len() as u64).pow(self.len)
+    }
+    fn get(&self, mut i: u64) -> Vec<u8> {
+        let n = self.chars.len() as u64;
+        let mut res = vec![];
+        for _ in 0..self.len {
+            res.push(self.chars[(i % n) as usize]);
+            i /= n;
+        }
+        res
+    }
+}
+
+// maps a truncated digest back into the space, `salt` differs for every
+// column of every table
+pub type Reduce = fn(digest: u64, salt: u64, size: u64) -> u64;
+
+pub fn reduce_add(digest: u64, salt: u64, size: u64) -> u64 {
+    digest.wrapping_add(salt) % size
+}
+
+// mixes the salt in first, which spreads short digests over a larger space
+pub fn reduce_mix(digest: u64, salt: u64, size: u64) -> u64 {
+    let mut x = digest ^ salt.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    x ^= x >> 31;
+    x = x.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x ^= x >> 29;
+    x % size
+}
+
+#[derive(Clone, Copy)]
+pub struct Params {
+    pub chain_len: usize,
+    // chains generated per table, the ones merging into another are dropped
+    pub chains: usize,
+    pub tables: usize,
+    pub reduce: Reduce,
+}
+
+pub struct Rainbow<H, S> {
+    hash: H,
+    // the digests are truncated to their first `bits` bits
+    bits: u32,
+    space: S,
+    params: Params,
+    // (start, end) pairs of every table, sorted by end
+    tables: Vec<Vec<(u64, u64)>>,
+}
+
+impl<H, T, S> Rainbow<H, S>
+where
+    H: Fn(&[u8]) -> T,
+    T: AsRef<[u8]>,
+    S: Space,
+{
+    pub fn new(hash: H, bits: u32, space: S, params: Params) -> Self {
+        let mut res = Self {
+            hash,
+            bits,
+            space,
+            params,
+            tables: vec![],
+        };
+        for t in 0..params.tables {
+            let mut ends = HashSet::new();
+            let mut chains = vec![];
+            for start in 0..params.chains as u64 {
+                let start = (params.reduce)(start, u64::MAX - t as u64, res.space.size());
+                let end = res.walk(t, start, 0, params.chain_len);
+                if ends.insert(end) {
+                    chains.push((start, end));
+                }
+            }
+            chains.sort_by_key(|(_, end)| *end);
+            res.tables.push(chains);
+        }
+        res
+    }
+
+    pub fn digest(&self, plaintext: &[u8]) -> u64 {
+        truncate((self.hash)(plaintext).as_ref(), self.bits)
+    }
+
+    fn salt(&self, t: usize, col: usize) -> u64 {
+        (t * self.params.chain_len + col) as u64
+    }
+
+    // from the plaintext `x` in column `from` to the one in column `to`
+    fn walk(&self, t: usize, mut x: u64, from: usize, to: usize) -> u64 {
+        for col in from..to {
+            let digest = self.digest(&self.space.get(x));
+            x = (self.params.reduce)(digest, self.salt(t, col), self.space.size());
+        }
+        x
+    }
+
+    // a plaintext hashing to `digest`, if the tables cover one
+    pub fn lookup(&self, digest: u64) -> Option<Vec<u8>> {
+        let size = self.space.size();
+        let len = self.params.chain_len;
+        // the columns closest to the end are the cheapest to try
+        for col in (0..len).rev() {
+            for (t, chains) in self.tables.iter().enumerate() {
+                let x = (self.params.reduce)(digest, self.salt(t, col), size);
+                let end = self.walk(t, x, col + 1, len);
+                let Ok(i) = chains.binary_search_by_key(&end, |(_, end)| *end) else {
+                    continue;
+                };
+                let plaintext = self.space.get(self.walk(t, chains[i].0, 0, col));
+                // a false alarm when another chain merges into this one
+                if self.digest(&plaintext) == digest {
+                    return Some(plaintext);
+                }
+            }
+        }
+        None
+    }
+
+    // The expected share of the space covered, from the number of distinct
+    // plaintexts in every column: m_i+1 = N (1 - e^(-m_i / N)).
+    pub fn success_rate(&self) -> f64 {
+        let n = self.space.size() as f64;
+        let mut miss = 1.0;
+        for chains in &self.tables {
+            let mut m = chains.len() as f64;
+            for _ in 0..self.params.chain_len {
+                miss *= 1.0 - m / n;
+                m = n * (1.0 - (-m / n).exp());
+            }
+        }
+        1.0 - miss
+    }
+
+    // a header line with the digest bits, chain length and table count, then
+    // one `table start end` line per chain
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut lines = vec![format!(
+            "{} {} {}",
+            self.bits, self.params.chain_len, self.params.tables
+        )];
+        for (t, chains) in self.tables.iter().enumerate() {
+            lines.extend(
+                chains
+                    .iter()
+                    .map(|(start, end)| format!("{t} {start} {end}")),
+            );
+        }
+        fs::write(path, lines.join("\n"))
+    }
+
+    // `hash`, `space` and the reduction must be the ones the table was made with
+    pub fn load(hash: H, space: S, reduce: Reduce, path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid =
+            |line: &str| io::Error::new(ErrorKind::InvalidData, format!("bad line `{line}`"));
+        let parse = |line: &str| -> io::Result<Vec<u64>> {
+            line.split(' ')
+                .map(|v| v.parse().map_err(|_| invalid(line)))
+                .collect()
+        };
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines();
+        let header = lines.next().unwrap_or_default();
+        let [bits, chain_len, count] = parse(header)?[..] else {
+            return Err(invalid(header));
+        };
+        // every table holds at least one chain, so a count beyond the number
+        // of chains can't come from `save`
+        if !(1..=64).contains(&bits) || count > text.lines().count() as u64 - 1 {
+            return Err(invalid(header));
+        }
+        let mut tables = vec![vec![]; count as usize];
+        for line in lines {
+            let [t, start, end] = parse(line)?[..] else {
+                return Err(invalid(line));
+            };
+            tables
+                .get_mut(t as usize)
+                .ok_or(invalid(line))?
+                .push((start, end));
+        }
+        for chains in tables.iter_mut() {
+            chains.sort_by_key(|(_, end)| *end);
+        }
+        let params = Params {
+            chain_len: chain_len as usize,
+            chains: tables.iter().map(|c| c.len()).max().unwrap_or(0),
+            tables: count as usize,
+            reduce,
+        };
+        Ok(Self {
+            hash,
+            bits: bits as u32,
+            space,
+            params,
+            tables,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        digest::Digest,
+        md4::Md4,
+        random::{seeded, RandomSource},
+        sha1::sha1,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_spaces() {
+        let pins = Pins { digits: 4 };
+        assert_eq!(pins.size(), 10000);
+        assert_eq!(pins.get(42), b"0042");
+        let words = Charset {
+            chars: b"ab".to_vec(),
+            len: 3,
+        };
+        assert_eq!(words.size(), 8);
+        assert_eq!(words.get(6), b"abb");
+    }
+
+    #[test]
+    fn test_pins() {
+        let params = Params {
+            chain_len: 50,
+            chains: 2000,
+            tables: 4,
+            reduce: reduce_mix,
+        };
+        let table = Rainbow::new(sha1, 32, Pins { digits: 5 }, params);
+        let expected = table.success_rate();
+        assert!(expected > 0.6);
+
+        let tries = 50;
+        let mut found = 0;
+        let mut rng = seeded(276);
+        for _ in 0..tries {
+            let pin = Pins { digits: 5 }.get(rng.below(0..100000) as u64);
+            let digest = table.digest(&pin);
+            if let Some(plaintext) = table.lookup(digest) {
+                assert_eq!(table.digest(&plaintext), digest);
+                found += 1;
+            }
+        }
+        let measured = found as f64 / tries as f64;
+        assert!(
+            (measured - expected).abs() < 0.2,
+            "{measured} vs {expected}"
+        );
+    }
+
+    #[test]
+    fn test_save_load() {
+        let space = || Charset {
+            chars: b"abcdefghijklmnopqrstuvwxyz".to_vec(),
+            len: 3,
+        };
+        let params = Params {
+            chain_len: 50,
+            chains: 200,
+            tables: 2,
+            reduce: reduce_add,
+        };
+        let table = Rainbow::new(Md4::digest, 24, space(), params);
+        let path = std::env::temp_dir().join(format!("rainbow_test_{}.txt", std::process::id()));
+        table.save(&path).unwrap();
+        let loaded = Rainbow::load(Md4::digest, space(), reduce_add, &path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.tables, table.tables);
+        assert_eq!(loaded.success_rate(), table.success_rate());
+
+        // a plaintext from the middle of a chain is always found
+        let (start, _) = table.tables[1][0];
+        let plaintext = space().get(table.walk(1, start, 0, 20));
+        let digest = loaded.digest(&plaintext);
+        assert_eq!(loaded.digest(&loaded.lookup(digest).unwrap()), digest);
+    }
+
+    #[test]
+    fn test_load_invalid() {
+        let path = std::env::temp_dir().join(format!("rainbow_invalid_{}.txt", std::process::id()));
+        for text in [
+            "",
+            "24 50",
+            "0 50 1\n0 1 2",
+            "65 50 1\n0 1 2",
+            "24 50 18446744073709551615\n0 1 2",
+            "24 50 1\n1 1 2",
+            "24 50 1\n0 x 2",
+        ] {
+            fs::write(&path, text).unwrap();
+            let err = Rainbow::load(
+                Md4::digest,
+                Charset {
+                    chars: vec![b'a'],
+                    len: 1,
+                },
+                reduce_add,
+                &path,
+            )
+            .err()
+            .unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidData, "{text}");
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
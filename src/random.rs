@@ -0,0 +1,66 @@
+#![allow(unused)]
+use std::ops::Range;
+
+use rand::{
+    rngs::{OsRng, StdRng},
+    Rng, SeedableRng,
+};
+use rand_core::RngCore;
+
+use crate::misc::rand_vec_from;
+
+// Where targets get their keys, IVs, prefixes and coin flips. Anything
+// implementing `RngCore` is one: `os()` for real use, `seeded(seed)` to replay
+// a run exactly, or a weak generator like a seeded MT19937.
+pub trait RandomSource: RngCore {
+    fn bytes(&mut self, len: usize) -> Vec<u8>
+    where
+        Self: Sized,
+    {
+        rand_vec_from(self, len)
+    }
+    fn below(&mut self, range: Range<usize>) -> usize
+    where
+        Self: Sized,
+    {
+        self.gen_range(range)
+    }
+    fn coin(&mut self) -> bool
+    where
+        Self: Sized,
+    {
+        self.gen()
+    }
+}
+
+impl<R: RngCore> RandomSource for R {}
+
+pub fn os() -> OsRng {
+    OsRng
+}
+
+pub fn seeded(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mt19937::MT19937;
+
+    use super::*;
+
+    #[test]
+    fn test_seeded() {
+        let mut a = seeded(7);
+        let mut b = seeded(7);
+        assert_eq!(a.bytes(32), b.bytes(32));
+        assert_eq!(a.below(5..21), b.below(5..21));
+        assert_eq!(a.coin(), b.coin());
+        assert_ne!(a.bytes(32), seeded(8).bytes(32));
+
+        let n = os().below(5..21);
+        assert!((5..21).contains(&n));
+        let mut mt = MT19937::new(5489);
+        assert_eq!(mt.bytes(4), 3499211612u32.to_le_bytes());
+    }
+}
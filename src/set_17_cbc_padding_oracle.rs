@@ -7,6 +7,7 @@ use crate::{
     },
     misc::rand_vec,
     pkcs::is_pkcs7_padding,
+    random::{os, seeded, RandomSource},
 };
 
 const BLOCK_SIZE: usize = 16;
@@ -17,9 +18,12 @@ struct Oracle {
 
 impl Oracle {
     pub fn new() -> Self {
+        Self::with_rng(&mut os())
+    }
+    pub fn with_rng(rng: &mut impl RandomSource) -> Self {
         Self {
-            initial_value: rand_vec(BLOCK_SIZE),
-            key: rand_vec(BLOCK_SIZE),
+            initial_value: rng.bytes(BLOCK_SIZE),
+            key: rng.bytes(BLOCK_SIZE),
         }
     }
     pub fn encrypt(&self, input: &[u8]) -> Vec<u8> {
@@ -66,31 +70,25 @@ fn crack(data: &[u8], initial_value: &[u8], oracle: &Oracle) -> Vec<u8> {
                     possible_guess.push(guess);
                 }
             }
-            if possible_guess.len() > 1 {
-                // we have multiple candidates, we use next byte to judge which is better
-                let padding_size = 1 + padding_size;
-                let mut final_guess = possible_guess[0];
-                'guess: for guess in &possible_guess {
-                    for next_guess in 0..=255 {
-                        arr[padding_start_index] =
-                            prev_block[padding_start_index] ^ guess ^ padding_size;
-                        arr[padding_start_index - 1] =
-                            prev_block[padding_start_index - 1] ^ next_guess ^ padding_size;
-                        for (index, n) in decrypted_block.iter().rev().enumerate() {
-                            arr[padding_start_index + index + 1] =
-                                prev_block[padding_start_index + index + 1] ^ n ^ padding_size;
-                        }
-                        if oracle.decrypt(&cur_block, &arr) {
-                            // println!("choose guess value: {guess}");
-                            final_guess = *guess;
-                            break 'guess;
-                        }
-                    }
-                }
-                possible_guess.clear();
-                possible_guess.push(final_guess);
+            if possible_guess.len() > 1 && padding_start_index > 0 {
+                // a wrong guess can also give a longer valid padding, like 02 02.
+                // changing the byte before the padding only breaks those
+                possible_guess.retain(|guess| {
+                    arr[padding_start_index] =
+                        prev_block[padding_start_index] ^ guess ^ padding_size;
+                    arr[padding_start_index - 1] ^= 0xff;
+                    let valid = oracle.decrypt(&cur_block, &arr);
+                    arr[padding_start_index - 1] ^= 0xff;
+                    valid
+                });
             }
-            decrypted_block.push(possible_guess[0]);
+            let [guess] = possible_guess[..] else {
+                panic!(
+                    "{} candidates for byte {padding_start_index}",
+                    possible_guess.len()
+                );
+            };
+            decrypted_block.push(guess);
             // println!("decrypted_block {:?}", decrypted_block);
         }
         decrypted_data.push(decrypted_block.into_iter().rev().collect());
@@ -120,14 +118,14 @@ mod tests {
 
     #[test]
     fn test_set_17() {
-        let oracle = Oracle::new();
-        let input = base64_to_u8(S[rand::random::<usize>() % 10]);
-        println!("input {input:?}");
-        let encrypt_data = oracle.encrypt(&input);
-        let decrypt_data = oracle.decrypt(&encrypt_data, &oracle.initial_value);
-        let crack_data = crack(&encrypt_data, &oracle.initial_value, &oracle);
-        let crack_data = pkcs7unpadding(&crack_data, BLOCK_SIZE as u8);
-        println!("crack {crack_data:?}");
-        assert_eq!(crack_data, input);
+        for seed in 0..4 {
+            let mut rng = seeded(seed);
+            let oracle = Oracle::with_rng(&mut rng);
+            let input = base64_to_u8(S[rng.below(0..10)]);
+            let encrypt_data = oracle.encrypt(&input);
+            let crack_data = crack(&encrypt_data, &oracle.initial_value, &oracle);
+            let crack_data = pkcs7unpadding(&crack_data, BLOCK_SIZE as u8);
+            assert_eq!(crack_data, input, "seed {seed}");
+        }
     }
 }
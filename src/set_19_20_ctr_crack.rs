@@ -1,8 +1,6 @@
 use crate::{
-    block_cipher_mode::encrypt_in_ctr_mode,
-    misc::{get_eng_histogram, rand_vec},
-    single_byte_xor_cipher::single_byte_xor_cipher_with_histogram,
-    xor::xor_data,
+    block_cipher_mode::encrypt_in_ctr_mode, misc::get_eng_histogram, random::RandomSource,
+    single_byte_xor_cipher::single_byte_xor_cipher_with_histogram, xor::xor_data,
 };
 
 const BLOCK_SIZE: usize = 16;
@@ -12,10 +10,10 @@ struct Oracle {
 }
 
 impl Oracle {
-    pub fn new(nonce: u64) -> Self {
+    pub fn with_rng(rng: &mut impl RandomSource, nonce: u64) -> Self {
         Self {
             nonce,
-            key: rand_vec(BLOCK_SIZE),
+            key: rng.bytes(BLOCK_SIZE),
         }
     }
     pub fn encrypt(&self, input: &[u8]) -> Vec<u8> {
@@ -50,7 +48,9 @@ pub fn crack_ctr_statically(input: &Vec<Vec<u8>>) -> Vec<Vec<u8>> {
 mod tests {
     use std::fs;
 
-    use crate::base64::base64_to_u8;
+    use rand_core::RngCore;
+
+    use crate::{base64::base64_to_u8, random::seeded};
 
     use super::*;
 
@@ -137,7 +137,7 @@ a terrible beauty is born.";
 
     #[test]
     fn test_set_19() {
-        let oracle = Oracle::new(0);
+        let oracle = Oracle::with_rng(&mut seeded(0), 0);
         let cipher_text: Vec<Vec<u8>> = SET_18_INPUT
             .split('\n')
             .map(|s| base64_to_u8(s))
@@ -159,25 +159,29 @@ a terrible beauty is born.";
 
     #[test]
     fn test_set_20() {
-        let oracle = Oracle::new(rand::random::<u64>());
         let s = fs::read_to_string("20.txt").unwrap();
         let expect = fs::read_to_string("20_expect.txt").unwrap();
-        let cipher_text: Vec<Vec<u8>> = s
-            .split('\n')
-            .map(|s| base64_to_u8(s))
-            .map(|data| oracle.encrypt(&data))
-            .collect();
-        let plain_text = crack_ctr_statically(&cipher_text);
-        let mut res = vec![];
-        for line in plain_text {
-            let s: String = line
-                .iter()
-                .filter(|n| **n != 0)
-                .map(|n| *n as char)
+        for seed in 0..4 {
+            let mut rng = seeded(seed);
+            let nonce = rng.next_u64();
+            let oracle = Oracle::with_rng(&mut rng, nonce);
+            let cipher_text: Vec<Vec<u8>> = s
+                .split('\n')
+                .map(|s| base64_to_u8(s))
+                .map(|data| oracle.encrypt(&data))
                 .collect();
-            res.push(s);
+            let plain_text = crack_ctr_statically(&cipher_text);
+            let mut res = vec![];
+            for line in plain_text {
+                let s: String = line
+                    .iter()
+                    .filter(|n| **n != 0)
+                    .map(|n| *n as char)
+                    .collect();
+                res.push(s);
+            }
+            let res = res.join("\n");
+            assert_eq!(res, expect, "seed {seed}");
         }
-        let res = res.join("\n");
-        assert_eq!(res, expect);
     }
 }
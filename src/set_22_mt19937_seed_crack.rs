@@ -1,38 +1,252 @@
+#![allow(unused)]
 use core::time;
-use std::{thread::sleep, time::SystemTime};
+use std::{
+    cell::Cell,
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+    thread::{self, sleep},
+    time::{Duration, SystemTime},
+};
 
-use crate::mt19937::MT19937;
+use crate::{
+    mt19937::{early_output, MT19937, N},
+    random::RandomSource,
+};
 
-fn timestamp_seed() -> (u32, u32) {
-    sleep(time::Duration::from_secs(rand::random::<u64>() % 10));
-    let seed = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as u32;
-    sleep(time::Duration::from_secs(rand::random::<u64>() % 10));
+pub trait Clock {
+    // the time since the unix epoch
+    fn now(&self) -> Duration;
+    fn sleep(&self, duration: Duration);
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+    }
+    fn sleep(&self, duration: Duration) {
+        sleep(duration)
+    }
+}
+
+// sleeping only moves the time forward
+pub struct MockClock {
+    now: Cell<Duration>,
+}
+
+impl MockClock {
+    pub fn new(now: Duration) -> Self {
+        Self {
+            now: Cell::new(now),
+        }
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+    fn sleep(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration)
+    }
+}
+
+// the unit of the timestamp the generator was seeded with, truncated to 32 bits
+#[derive(Clone, Copy, Debug)]
+pub enum Precision {
+    Seconds,
+    Millis,
+    Micros,
+}
+
+impl Precision {
+    pub fn ticks(self, time: Duration) -> u64 {
+        match self {
+            Precision::Seconds => time.as_secs(),
+            Precision::Millis => time.as_millis() as u64,
+            Precision::Micros => time.as_micros() as u64,
+        }
+    }
+}
+
+fn timestamp_seed_with(rng: &mut impl RandomSource, clock: &impl Clock) -> (u32, u32) {
+    clock.sleep(time::Duration::from_secs(rng.below(0..10) as u64));
+    let seed = Precision::Seconds.ticks(clock.now()) as u32;
+    clock.sleep(time::Duration::from_secs(rng.below(0..10) as u64));
     let mut rng = MT19937::new(seed);
     (seed, rng.gen_u32())
 }
 
+// `outputs` are sorted by index, without repeats
+fn matches(seed: u32, outputs: &[(usize, u32)]) -> bool {
+    let (index, value) = outputs[0];
+    if index < N - 397 && early_output(seed, index) != value {
+        return false;
+    }
+    let mut rng = MT19937::new(seed);
+    let mut next = 0;
+    for &(index, value) in outputs {
+        for _ in next..index {
+            rng.gen_u32();
+        }
+        if rng.gen_u32() != value {
+            return false;
+        }
+        next = index + 1;
+    }
+    true
+}
+
+// The timestamp in `window`, in ticks of `precision`, whose truncation to 32
+// bits seeds a generator producing `outputs`, (index, value) pairs counted from
+// the first output after seeding. A window longer than 2^32 ticks covers every
+// seed, the earliest tick for it is returned. The ticks are split between the
+// available threads.
+pub fn crack_time_seed(
+    outputs: &[(usize, u32)],
+    window: Range<Duration>,
+    precision: Precision,
+) -> Option<u64> {
+    let mut outputs = outputs.to_vec();
+    outputs.sort();
+    outputs.dedup();
+    // two values for one index can't come from any seed
+    if outputs.is_empty() || outputs.windows(2).any(|w| w[0].0 == w[1].0) {
+        return None;
+    }
+    let start = precision.ticks(window.start);
+    let len = precision
+        .ticks(window.end)
+        .saturating_sub(start)
+        .min(1 << 32);
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get()) as u64;
+    let chunk = len.div_ceil(threads).max(1);
+    // the earliest tick found so far, the threads stop once they are past it
+    let found = AtomicU64::new(u64::MAX);
+    thread::scope(|s| {
+        for t in 0..threads {
+            let outputs = &outputs;
+            let found = &found;
+            s.spawn(move || {
+                for i in t * chunk..((t + 1) * chunk).min(len) {
+                    if i >= found.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    if matches((start + i) as u32, outputs) {
+                        found.fetch_min(i, Ordering::Relaxed);
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    match found.into_inner() {
+        u64::MAX => None,
+        i => Some(start + i),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::random::seeded;
+
     use super::*;
 
+    fn nth(seed: u32, index: usize) -> u32 {
+        let mut rng = MT19937::new(seed);
+        for _ in 0..index {
+            rng.gen_u32();
+        }
+        rng.gen_u32()
+    }
+
     #[test]
     fn test_crack_timestamp_seed() {
-        let (true_seed, cipher_text) = timestamp_seed();
-        let now = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as u32;
-        let mut seed = now;
-        loop {
-            let val = MT19937::new(seed).gen_u32();
-            if cipher_text == val {
-                assert_eq!(seed, true_seed);
-                return;
-            }
-            seed -= 1;
-        }
+        let clock = MockClock::new(Duration::from_secs(1_700_000_000));
+        let (true_seed, cipher_text) = timestamp_seed_with(&mut seeded(22), &clock);
+        let now = clock.now();
+        let window = now - Duration::from_secs(3600)..now + Duration::from_secs(1);
+        let seed = crack_time_seed(&[(0, cipher_text)], window, Precision::Seconds);
+        assert_eq!(seed, Some(true_seed as u64));
+    }
+
+    #[test]
+    fn test_precision() {
+        let time = Duration::from_micros(1_700_000_123_456_789);
+        assert_eq!(Precision::Seconds.ticks(time), 1_700_000_123);
+        assert_eq!(Precision::Millis.ticks(time), 1_700_000_123_456);
+        assert_eq!(Precision::Micros.ticks(time), 1_700_000_123_456_789);
+    }
+
+    #[test]
+    fn test_crack_millis_with_offset() {
+        let clock = MockClock::new(Duration::from_millis(1_700_000_123_456));
+        let ticks = Precision::Millis.ticks(clock.now());
+        let mut rng = MT19937::new(ticks as u32);
+        let outputs = (0..2000).map(|_| rng.gen_u32()).collect::<Vec<_>>();
+        // a few outputs from deep into the stream
+        let observed = [
+            (1500, outputs[1500]),
+            (700, outputs[700]),
+            (1999, outputs[1999]),
+        ];
+
+        clock.sleep(Duration::from_secs(5));
+        let now = clock.now();
+        let window = now - Duration::from_secs(10)..now;
+        assert_eq!(
+            crack_time_seed(&observed, window.clone(), Precision::Millis),
+            Some(ticks)
+        );
+        // the same value at a different position doesn't match
+        assert_eq!(
+            crack_time_seed(&[(1501, outputs[1500])], window.clone(), Precision::Millis),
+            None
+        );
+
+        // an output seen twice counts once, two values for one index never match
+        let repeated = [observed[0], observed[1], observed[0]];
+        assert_eq!(
+            crack_time_seed(&repeated, window.clone(), Precision::Millis),
+            Some(ticks)
+        );
+        let conflicting = [observed[0], (1500, outputs[1501])];
+        assert_eq!(
+            crack_time_seed(&conflicting, window, Precision::Millis),
+            None
+        );
+    }
+
+    #[test]
+    fn test_crack_micros() {
+        let clock = MockClock::new(Duration::from_micros(1_700_000_123_456_789));
+        let ticks = Precision::Micros.ticks(clock.now());
+        let observed = [(5, nth(ticks as u32, 5))];
+        clock.sleep(Duration::from_millis(20));
+        let now = clock.now();
+        let window = now - Duration::from_millis(100)..now;
+        assert_eq!(
+            crack_time_seed(&observed, window, Precision::Micros),
+            Some(ticks)
+        );
+    }
+
+    // every 32-bit seed, takes a while. One output isn't enough to tell all
+    // of them apart
+    #[test]
+    #[ignore]
+    fn test_crack_full_space() {
+        let seed = 0xfedc_ba98u32;
+        let observed = [(0, nth(seed, 0)), (1, nth(seed, 1))];
+        let window = Duration::ZERO..Duration::from_micros(1 << 33);
+        assert_eq!(
+            crack_time_seed(&observed, window, Precision::Micros),
+            Some(seed as u64),
+            "seed {seed}"
+        );
     }
 }
@@ -24,4 +24,19 @@ mod tests {
             assert_eq!(v1, v2);
         }
     }
+
+    #[test]
+    fn test_recover_earlier_outputs() {
+        for seed in [0, 5489, 0x9e37_79b9, u32::MAX] {
+            let mut rng = MT19937::new(seed);
+            let tokens = (0..1000).map(|_| rng.gen_u32()).collect::<Vec<_>>();
+            // the 624 outputs after the tokens are enough to rewind to the start
+            let mut clone = crack_mt19937(&mut rng);
+            clone.rewind(1000 + 624);
+            assert_eq!(clone.seed(), Some(seed), "seed {seed}");
+            for token in tokens {
+                assert_eq!(clone.gen_u32(), token, "seed {seed}");
+            }
+        }
+    }
 }
@@ -0,0 +1,128 @@
+#![allow(unused)]
+use std::{cell::RefCell, time::SystemTime};
+
+use rand::rngs::OsRng;
+
+use crate::{
+    misc::rand_vec,
+    mt19937::MT19937,
+    random::{os, seeded, RandomSource},
+};
+
+// every output of the generator gives 4 bytes of keystream
+fn keystream(seed: u16, len: usize) -> Vec<u8> {
+    let mut rng = MT19937::new(seed as u32);
+    let mut res = vec![];
+    while res.len() < len {
+        res.extend(rng.gen_u32().to_le_bytes());
+    }
+    res.truncate(len);
+    res
+}
+
+// encryption and decryption are the same
+pub fn mt19937_cipher(seed: u16, data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .zip(keystream(seed, data.len()))
+        .map(|(d, k)| d ^ k)
+        .collect()
+}
+
+struct Oracle<R> {
+    seed: u16,
+    rng: RefCell<R>,
+}
+
+impl Oracle<OsRng> {
+    pub fn new() -> Self {
+        Self::with_rng(os())
+    }
+}
+
+impl<R: RandomSource> Oracle<R> {
+    pub fn with_rng(mut rng: R) -> Self {
+        Self {
+            seed: rng.next_u32() as u16,
+            rng: RefCell::new(rng),
+        }
+    }
+    // a random prefix of 5 to 20 bytes before the attacker's plaintext
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let mut rng = self.rng.borrow_mut();
+        let len = rng.below(5..21);
+        let prefix = rng.bytes(len);
+        mt19937_cipher(self.seed, &[&prefix[..], plaintext].concat())
+    }
+}
+
+// the keystream at the end of the ciphertext is known, only 2^16 seeds have to
+// be tried to find the one generating it
+pub fn recover_seed(ciphertext: &[u8], known_suffix: &[u8]) -> Option<u16> {
+    let start = ciphertext.len().checked_sub(known_suffix.len())?;
+    (0..=u16::MAX).find(|seed| mt19937_cipher(*seed, ciphertext)[start..] == *known_suffix)
+}
+
+fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as u32
+}
+
+// a "password reset token" made of MT19937 output seeded with the time
+pub fn reset_token(timestamp: u32) -> [u8; 16] {
+    let mut rng = MT19937::new(timestamp);
+    let mut token = [0; 16];
+    for chunk in token.chunks_mut(4) {
+        chunk.copy_from_slice(&rng.gen_u32().to_le_bytes());
+    }
+    token
+}
+
+// the timestamp within `window` seconds of `now` that generated `token`, if any
+pub fn find_token_seed(token: &[u8], now: u32, window: u32) -> Option<u32> {
+    (now.saturating_sub(window)..=now.saturating_add(window))
+        .find(|t| reset_token(*t)[..] == *token)
+}
+
+pub fn is_time_seeded_token(token: &[u8]) -> bool {
+    find_token_seed(token, now(), 24 * 60 * 60).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mt19937_cipher() {
+        let plaintext = b"the same function encrypts and decrypts";
+        let ciphertext = mt19937_cipher(1234, plaintext);
+        assert_ne!(&ciphertext[..], &plaintext[..]);
+        assert_eq!(mt19937_cipher(1234, &ciphertext), plaintext);
+        assert_ne!(mt19937_cipher(1235, &ciphertext), plaintext);
+    }
+
+    #[test]
+    fn test_recover_seed() {
+        let oracle = Oracle::new();
+        let known = b"AAAAAAAAAAAAAA";
+        let ciphertext = oracle.encrypt(known);
+        assert_eq!(recover_seed(&ciphertext, known), Some(oracle.seed));
+
+        let oracle = Oracle::with_rng(seeded(1));
+        let first = oracle.encrypt(known);
+        assert_eq!(Oracle::with_rng(seeded(1)).encrypt(known), first);
+        assert_eq!(recover_seed(&first, known), Some(oracle.seed));
+    }
+
+    #[test]
+    fn test_reset_token() {
+        let now = now();
+        let token = reset_token(now - 600);
+        assert_eq!(find_token_seed(&token, now, 3600), Some(now - 600));
+        assert!(is_time_seeded_token(&token));
+
+        let token = rand_vec(16);
+        assert_eq!(find_token_seed(&token, now, 3600), None);
+    }
+}
@@ -0,0 +1,108 @@
+#![allow(unused)]
+use std::ops::Range;
+
+use crate::{
+    length_extension,
+    random::{os, RandomSource},
+    sha1::Sha1,
+};
+
+pub fn sha1_mac(key: &[u8], message: &[u8]) -> [u8; 20] {
+    length_extension::secret_prefix_mac::<Sha1>(key, message)
+        .try_into()
+        .unwrap()
+}
+
+pub fn verify_sha1_mac(key: &[u8], message: &[u8], mac: &[u8; 20]) -> bool {
+    sha1_mac(key, message) == *mac
+}
+
+struct Oracle {
+    key: Vec<u8>,
+}
+
+impl Oracle {
+    pub fn new() -> Self {
+        Self::with_rng(&mut os())
+    }
+    pub fn with_rng(rng: &mut impl RandomSource) -> Self {
+        // the attacker doesn't know the key length either
+        let key_len = rng.below(1..33);
+        Self {
+            key: rng.bytes(key_len),
+        }
+    }
+    pub fn sign(&self, message: &[u8]) -> [u8; 20] {
+        sha1_mac(&self.key, message)
+    }
+    pub fn verify(&self, message: &[u8], mac: &[u8; 20]) -> bool {
+        verify_sha1_mac(&self.key, message, mac)
+    }
+}
+
+// forge the mac of `message || glue_padding || extension` assuming the
+// secret key is `key_len` bytes long
+pub fn forge_with_key_len(
+    key_len: usize,
+    message: &[u8],
+    mac: &[u8; 20],
+    extension: &[u8],
+) -> (Vec<u8>, [u8; 20]) {
+    let (forged_message, forged_mac) =
+        length_extension::forge_with_key_len::<Sha1>(key_len, message, mac, extension);
+    (forged_message, forged_mac.try_into().unwrap())
+}
+
+pub fn length_extension<F>(
+    message: &[u8],
+    mac: &[u8; 20],
+    extension: &[u8],
+    key_lens: Range<usize>,
+    verify: F,
+) -> Option<(Vec<u8>, [u8; 20])>
+where
+    F: Fn(&[u8], &[u8; 20]) -> bool,
+{
+    let (forged_message, forged_mac) = length_extension::length_extension::<Sha1, _>(
+        message,
+        mac,
+        extension,
+        key_lens,
+        |m, mac| verify(m, mac.try_into().unwrap()),
+    )?;
+    Some((forged_message, forged_mac.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &[u8] =
+        b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+
+    #[test]
+    fn test_sha1_mac() {
+        let oracle = Oracle::new();
+        let mac = oracle.sign(MESSAGE);
+        assert!(oracle.verify(MESSAGE, &mac));
+
+        let mut tampered = MESSAGE.to_vec();
+        tampered[0] ^= 1;
+        assert!(!oracle.verify(&tampered, &mac));
+        assert_ne!(sha1_mac(b"other key", MESSAGE), mac);
+    }
+
+    #[test]
+    fn test_length_extension() {
+        let oracle = Oracle::new();
+        let mac = oracle.sign(MESSAGE);
+        let (forged_message, forged_mac) =
+            length_extension(MESSAGE, &mac, b";admin=true", 0..64, |m, mac| {
+                oracle.verify(m, mac)
+            })
+            .expect("cannot forge the mac");
+        assert!(forged_message.starts_with(MESSAGE));
+        assert!(forged_message.ends_with(b";admin=true"));
+        assert!(oracle.verify(&forged_message, &forged_mac));
+    }
+}
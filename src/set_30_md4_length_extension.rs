@@ -0,0 +1,105 @@
+#![allow(unused)]
+use std::ops::Range;
+
+use crate::{
+    length_extension,
+    md4::Md4,
+    random::{os, RandomSource},
+};
+
+pub fn md4_mac(key: &[u8], message: &[u8]) -> [u8; 16] {
+    length_extension::secret_prefix_mac::<Md4>(key, message)
+        .try_into()
+        .unwrap()
+}
+
+pub fn verify_md4_mac(key: &[u8], message: &[u8], mac: &[u8; 16]) -> bool {
+    md4_mac(key, message) == *mac
+}
+
+struct Oracle {
+    key: Vec<u8>,
+}
+
+impl Oracle {
+    pub fn new() -> Self {
+        Self::with_rng(&mut os())
+    }
+    pub fn with_rng(rng: &mut impl RandomSource) -> Self {
+        let key_len = rng.below(1..33);
+        Self {
+            key: rng.bytes(key_len),
+        }
+    }
+    pub fn sign(&self, message: &[u8]) -> [u8; 16] {
+        md4_mac(&self.key, message)
+    }
+    pub fn verify(&self, message: &[u8], mac: &[u8; 16]) -> bool {
+        verify_md4_mac(&self.key, message, mac)
+    }
+}
+
+// same as the SHA-1 forgery, except MD4 encodes the bit length little-endian
+pub fn forge_with_key_len(
+    key_len: usize,
+    message: &[u8],
+    mac: &[u8; 16],
+    extension: &[u8],
+) -> (Vec<u8>, [u8; 16]) {
+    let (forged_message, forged_mac) =
+        length_extension::forge_with_key_len::<Md4>(key_len, message, mac, extension);
+    (forged_message, forged_mac.try_into().unwrap())
+}
+
+pub fn length_extension<F>(
+    message: &[u8],
+    mac: &[u8; 16],
+    extension: &[u8],
+    key_lens: Range<usize>,
+    verify: F,
+) -> Option<(Vec<u8>, [u8; 16])>
+where
+    F: Fn(&[u8], &[u8; 16]) -> bool,
+{
+    let (forged_message, forged_mac) = length_extension::length_extension::<Md4, _>(
+        message,
+        mac,
+        extension,
+        key_lens,
+        |m, mac| verify(m, mac.try_into().unwrap()),
+    )?;
+    Some((forged_message, forged_mac.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &[u8] =
+        b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+
+    #[test]
+    fn test_md4_mac() {
+        let oracle = Oracle::new();
+        let mac = oracle.sign(MESSAGE);
+        assert!(oracle.verify(MESSAGE, &mac));
+
+        let mut tampered = MESSAGE.to_vec();
+        tampered[0] ^= 1;
+        assert!(!oracle.verify(&tampered, &mac));
+    }
+
+    #[test]
+    fn test_length_extension() {
+        let oracle = Oracle::new();
+        let mac = oracle.sign(MESSAGE);
+        let (forged_message, forged_mac) =
+            length_extension(MESSAGE, &mac, b";admin=true", 0..64, |m, mac| {
+                oracle.verify(m, mac)
+            })
+            .expect("cannot forge the mac");
+        assert!(forged_message.starts_with(MESSAGE));
+        assert!(forged_message.ends_with(b";admin=true"));
+        assert!(oracle.verify(&forged_message, &forged_mac));
+    }
+}
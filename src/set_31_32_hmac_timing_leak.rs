@@ -0,0 +1,419 @@
+#![allow(unused)]
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, sleep, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    hmac::hmac,
+    misc::{from_hex, to_hex},
+    sha1::Sha1,
+};
+
+const HMAC_LEN: usize = 20;
+
+// compare byte by byte, bail out on the first mismatch and sleep `delay`
+// after every matching byte
+pub fn insecure_compare(a: &[u8], b: &[u8], delay: Duration) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    for (x, y) in a.iter().zip(b) {
+        if x != y {
+            return false;
+        }
+        sleep(delay);
+    }
+    true
+}
+
+// a local stand-in for a web service that checks
+// `GET /test?file=...&signature=...` against HMAC-SHA1(key, file)
+pub struct Server {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    // `tag_len` truncates the HMAC, the full tag is `HMAC_LEN` bytes
+    pub fn start(key: &[u8], delay: Duration, tag_len: usize) -> Self {
+        assert!(tag_len > 0 && tag_len <= HMAC_LEN);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let key = key.to_vec();
+        let handle = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        handle_request(stream, &key, delay, tag_len);
+                    }
+                }
+            })
+        };
+        Self {
+            addr,
+            stop,
+            handle: Some(handle),
+        }
+    }
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // wake up the accepting thread
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn handle_request(mut stream: TcpStream, key: &[u8], delay: Duration, tag_len: usize) {
+    let mut request_line = String::new();
+    if BufReader::new(&stream)
+        .read_line(&mut request_line)
+        .is_err()
+    {
+        return;
+    }
+    let status = match parse_query(&request_line) {
+        Some((file, signature)) => {
+            let mac = hmac::<Sha1>(key, file.as_bytes());
+            if insecure_compare(&mac[..tag_len], &signature, delay) {
+                "200 OK"
+            } else {
+                "500 Internal Server Error"
+            }
+        }
+        None => "400 Bad Request",
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+    );
+}
+
+fn parse_query(request_line: &str) -> Option<(String, Vec<u8>)> {
+    let path = request_line.split_whitespace().nth(1)?;
+    let query = path.strip_prefix("/test?")?;
+    let mut file = None;
+    let mut signature = None;
+    for pair in query.split('&') {
+        match pair.split_once('=')? {
+            ("file", v) => file = Some(v.to_string()),
+            ("signature", v) => signature = Some(from_hex(v)?),
+            _ => {}
+        }
+    }
+    Some((file?, signature?))
+}
+
+// send one request, return whether the signature was accepted and how long
+// the server took to answer
+pub fn request(addr: SocketAddr, file: &str, signature: &[u8]) -> io::Result<(bool, Duration)> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    let request = format!(
+        "GET /test?file={file}&signature={} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        to_hex(signature)
+    );
+    let start = Instant::now();
+    stream.write_all(request.as_bytes())?;
+    let mut status_line = String::new();
+    BufReader::new(&stream).read_line(&mut status_line)?;
+    let elapsed = start.elapsed();
+    Ok((
+        status_line.split_whitespace().nth(1) == Some("200"),
+        elapsed,
+    ))
+}
+
+pub struct TimingAttackConfig {
+    // requests sent for each candidate in the first pass over all 256 values
+    pub first_pass: usize,
+    // how many of the slowest candidates get measured again
+    pub finalists: usize,
+    // requests sent for each finalist
+    pub samples: usize,
+    // how many guesses may be thrown away before giving up
+    pub max_backtracks: usize,
+}
+
+impl Default for TimingAttackConfig {
+    fn default() -> Self {
+        Self {
+            first_pass: 1,
+            finalists: 8,
+            samples: 7,
+            max_backtracks: 32,
+        }
+    }
+}
+
+fn median(durations: &mut [Duration]) -> Duration {
+    durations.sort();
+    durations[durations.len() / 2]
+}
+
+// the timing of every guess, or Err with the signature the server accepted
+type Timings = Result<Vec<(u8, Duration)>, Vec<u8>>;
+
+// Time every guess for position `i` `n` times, a round over all of them at a
+// time so that a slow stretch of the machine hits them alike, and keep the
+// median of each. A single lucky or unlucky answer doesn't move it.
+fn measure(
+    addr: SocketAddr,
+    file: &str,
+    signature: &[u8],
+    i: usize,
+    guesses: &[u8],
+    n: usize,
+) -> io::Result<Timings> {
+    let mut signature = signature.to_vec();
+    let mut samples = vec![Vec::with_capacity(n); guesses.len()];
+    for _ in 0..n {
+        for (guess, t) in guesses.iter().zip(samples.iter_mut()) {
+            signature[i] = *guess;
+            let (ok, elapsed) = request(addr, file, &signature)?;
+            if ok {
+                return Ok(Err(signature));
+            }
+            t.push(elapsed);
+        }
+    }
+    Ok(Ok(guesses
+        .iter()
+        .copied()
+        .zip(samples.iter_mut().map(|t| median(t)))
+        .collect()))
+}
+
+// recover the first `tag_len` bytes of HMAC-SHA1(key, file) byte by byte
+//
+// for every position all 256 values are timed, the slowest few are timed
+// again and the slowest one wins. A correct byte makes the
+// server sleep one more round, so the winner should stand out from the other
+// finalists by about one delay; when it doesn't, the previous byte was a false
+// positive and we backtrack. None when it had to backtrack more than
+// `config.max_backtracks` times.
+pub fn recover_signature(
+    addr: SocketAddr,
+    file: &str,
+    tag_len: usize,
+    config: &TimingAttackConfig,
+) -> io::Result<Option<Vec<u8>>> {
+    // every candidate needs at least one timing
+    assert!(config.first_pass > 0 && config.samples > 0 && config.finalists > 0);
+    let mut known: Vec<u8> = vec![];
+    let mut rejected: Vec<HashSet<u8>> = vec![HashSet::new(); tag_len];
+    // the gap of the winning byte over the runner-up at position 0, which is
+    // the per-byte delay as seen from the client
+    let mut step: Option<Duration> = None;
+    let mut backtracks = 0;
+
+    while known.len() < tag_len {
+        if backtracks > config.max_backtracks {
+            return Ok(None);
+        }
+        let i = known.len();
+        let mut signature = known.clone();
+        signature.resize(tag_len, 0);
+
+        let guesses = (0..=255u8)
+            .filter(|g| !rejected[i].contains(g))
+            .collect::<Vec<_>>();
+        let mut first_pass = match measure(addr, file, &signature, i, &guesses, config.first_pass)?
+        {
+            Ok(timings) => timings,
+            Err(signature) => return Ok(Some(signature)),
+        };
+        // the status code already tells us about the last byte
+        if first_pass.is_empty() || i == tag_len - 1 {
+            if !backtrack(&mut known, &mut rejected, &mut step) {
+                return Ok(None);
+            }
+            backtracks += 1;
+            continue;
+        }
+        let floor = median(&mut first_pass.iter().map(|(_, d)| *d).collect::<Vec<_>>());
+
+        first_pass.sort_by_key(|(_, d)| std::cmp::Reverse(*d));
+        let guesses = first_pass
+            .iter()
+            .take(config.finalists)
+            .map(|(g, _)| *g)
+            .collect::<Vec<_>>();
+        let mut finalists = match measure(addr, file, &signature, i, &guesses, config.samples)? {
+            Ok(timings) => timings,
+            Err(signature) => return Ok(Some(signature)),
+        };
+        finalists.sort_by_key(|(_, d)| std::cmp::Reverse(*d));
+
+        // Timings drift over a run, so the winner is compared with the other
+        // finalists measured right along with it rather than with the first
+        // pass. They are wrong guesses unless the winner is.
+        let best = finalists[0];
+        let mut others = finalists[1..].iter().map(|(_, d)| *d).collect::<Vec<_>>();
+        let typical = if others.is_empty() {
+            floor
+        } else {
+            median(&mut others)
+        };
+        let runner_up = others.iter().max().copied().unwrap_or(floor);
+        let lowest = others.iter().min().copied().unwrap_or(floor);
+        let gap = best.1.saturating_sub(typical);
+        let accepted = match step {
+            Some(step) => gap >= step / 2,
+            // a first byte lost in the noise would set the bar for all the
+            // others to nothing, the right one leads the runner-up by more
+            // than the other finalists differ among themselves
+            None => best.1.saturating_sub(runner_up) > runner_up.saturating_sub(lowest),
+        };
+        if accepted {
+            step.get_or_insert(gap);
+            known.push(best.0);
+        } else {
+            if known.is_empty() {
+                rejected[0].insert(best.0);
+            } else if !backtrack(&mut known, &mut rejected, &mut step) {
+                return Ok(None);
+            }
+            backtracks += 1;
+        }
+    }
+    // all bytes are recovered but the server still rejects the signature
+    Ok(None)
+}
+
+fn backtrack(
+    known: &mut Vec<u8>,
+    rejected: &mut [HashSet<u8>],
+    step: &mut Option<Duration>,
+) -> bool {
+    let i = known.len();
+    let Some(wrong) = known.pop() else {
+        return false;
+    };
+    rejected[i - 1].insert(wrong);
+    for r in rejected[i..].iter_mut() {
+        r.clear();
+    }
+    if known.is_empty() {
+        *step = None;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insecure_compare() {
+        let delay = Duration::from_millis(5);
+        assert!(insecure_compare(b"abc", b"abc", delay));
+        assert!(!insecure_compare(b"abc", b"abd", delay));
+        assert!(!insecure_compare(b"abc", b"ab", delay));
+
+        let start = Instant::now();
+        insecure_compare(b"abcd", b"abxx", delay);
+        assert!(start.elapsed() >= delay * 2);
+    }
+
+    #[test]
+    fn test_server() {
+        let key = b"YELLOW SUBMARINE";
+        let server = Server::start(key, Duration::ZERO, HMAC_LEN);
+        let mac = hmac::<Sha1>(key, b"foo");
+        assert!(request(server.addr(), "foo", &mac).unwrap().0);
+        assert!(!request(server.addr(), "bar", &mac).unwrap().0);
+        assert!(!request(server.addr(), "foo", &mac[..10]).unwrap().0);
+    }
+
+    #[test]
+    fn test_recover_signature() {
+        let key = crate::misc::rand_vec(16);
+        let tag_len = 3;
+        let server = Server::start(&key, Duration::from_millis(5), tag_len);
+        let signature = recover_signature(
+            server.addr(),
+            "foo",
+            tag_len,
+            &TimingAttackConfig::default(),
+        )
+        .unwrap()
+        .expect("cannot recover the signature");
+        assert_eq!(signature, hmac::<Sha1>(&key, b"foo")[..tag_len]);
+    }
+
+    // Loopback round trips vary by about 120us between the fastest and slowest
+    // percent, a 50us sleep is lost in that unless many samples per candidate
+    // are taken. Whether it is found depends on the load of the machine, run
+    // with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_recover_signature_below_jitter() {
+        let key = crate::misc::rand_vec(16);
+        let tag_len = 3;
+        let server = Server::start(&key, Duration::from_micros(50), tag_len);
+        let config = TimingAttackConfig {
+            first_pass: 9,
+            finalists: 16,
+            samples: 51,
+            ..Default::default()
+        };
+        let signature = recover_signature(server.addr(), "foo", tag_len, &config)
+            .unwrap()
+            .expect("cannot recover the signature");
+        assert_eq!(signature, hmac::<Sha1>(&key, b"foo")[..tag_len]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_recover_signature_without_samples() {
+        let config = TimingAttackConfig {
+            samples: 0,
+            ..Default::default()
+        };
+        let _ = recover_signature("127.0.0.1:1".parse().unwrap(), "foo", 3, &config);
+    }
+
+    #[test]
+    fn test_recover_signature_gives_up() {
+        // without a delay nothing stands out and every guess gets rejected
+        let server = Server::start(b"YELLOW SUBMARINE", Duration::ZERO, 3);
+        let config = TimingAttackConfig {
+            max_backtracks: 4,
+            ..Default::default()
+        };
+        assert_eq!(
+            recover_signature(server.addr(), "foo", 3, &config).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_request_without_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        assert!(request(addr, "foo", b"").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+#![allow(unused)]
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+    merkle_damgard::{Compress, MerkleDamgard},
+    random::{os, RandomSource},
+};
+
+// two different blocks compressing `state` to the same next state, found by
+// hashing random blocks until the birthday bound catches up
+pub fn block_collision<C>(c: &C, state: C::State) -> (Vec<u8>, Vec<u8>, C::State)
+where
+    C: Compress,
+    C::State: Hash + Eq,
+{
+    block_collision_with(&mut os(), c, state)
+}
+
+pub fn block_collision_with<C>(
+    rng: &mut impl RandomSource,
+    c: &C,
+    state: C::State,
+) -> (Vec<u8>, Vec<u8>, C::State)
+where
+    C: Compress,
+    C::State: Hash + Eq,
+{
+    let mut seen: HashMap<C::State, Vec<u8>> = HashMap::new();
+    loop {
+        let block = rng.bytes(C::BLOCK_SIZE);
+        let mut next = state;
+        c.compress(&mut next, &block);
+        match seen.get(&next) {
+            Some(other) if *other != block => return (block, other.clone(), next),
+            Some(_) => {}
+            None => {
+                seen.insert(next, block);
+            }
+        }
+    }
+}
+
+// A chain of block collisions: every message picking one block of each pair
+// reaches the same state, so n collisions give 2^n colliding messages.
+pub struct MultiCollision<C: Compress> {
+    pub pairs: Vec<(Vec<u8>, Vec<u8>)>,
+    pub state: C::State,
+}
+
+impl<C> MultiCollision<C>
+where
+    C: Compress,
+    C::State: Hash + Eq,
+{
+    pub fn new(c: &C, iv: C::State, n: usize) -> Self {
+        Self::with_rng(&mut os(), c, iv, n)
+    }
+    pub fn with_rng(rng: &mut impl RandomSource, c: &C, iv: C::State, n: usize) -> Self {
+        let mut res = Self {
+            pairs: vec![],
+            state: iv,
+        };
+        for _ in 0..n {
+            res.extend_with(rng, c);
+        }
+        res
+    }
+    // one more collision doubles the number of messages
+    pub fn extend(&mut self, c: &C) {
+        self.extend_with(&mut os(), c)
+    }
+    pub fn extend_with(&mut self, rng: &mut impl RandomSource, c: &C) {
+        let (b1, b2, next) = block_collision_with(rng, c, self.state);
+        self.pairs.push((b1, b2));
+        self.state = next;
+    }
+    pub fn len(&self) -> usize {
+        1 << self.pairs.len()
+    }
+    // the message picking the second block of pair `j` when bit `j` of `i` is set
+    pub fn message(&self, i: usize) -> Vec<u8> {
+        self.pairs
+            .iter()
+            .enumerate()
+            .flat_map(|(j, (b1, b2))| if i >> j & 1 == 1 { b2 } else { b1 })
+            .copied()
+            .collect()
+    }
+    pub fn messages(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        (0..self.len()).map(|i| self.message(i))
+    }
+}
+
+// the state of `g` after the blocks of `message`, without padding
+fn chain<G: Compress>(g: &G, message: &[u8]) -> G::State {
+    let mut state = g.iv();
+    for block in message.chunks(G::BLOCK_SIZE) {
+        g.compress(&mut state, block);
+    }
+    state
+}
+
+// Two messages colliding under both `f` and `g`, so `f(m) || g(m)` collides
+// too. Only `f` is attacked: 2^(b/2) messages colliding under `f`, where b is
+// the state size of `g` in bits, hold a collision of `g` with good chance. The
+// cost is about that of a birthday attack on `g` alone.
+pub fn cascade_collision<F, G>(f: &F, g: &G) -> (Vec<u8>, Vec<u8>)
+where
+    F: Compress,
+    F::State: Hash + Eq,
+    G: Compress,
+    G::State: Hash + Eq,
+{
+    cascade_collision_with(&mut os(), f, g)
+}
+
+pub fn cascade_collision_with<F, G>(rng: &mut impl RandomSource, f: &F, g: &G) -> (Vec<u8>, Vec<u8>)
+where
+    F: Compress,
+    F::State: Hash + Eq,
+    G: Compress,
+    G::State: Hash + Eq,
+{
+    // the blocks of `f` must line up with the ones of `g`
+    assert_eq!(F::BLOCK_SIZE, G::BLOCK_SIZE);
+    let bits = g.to_bytes(&g.iv()).len() * 8;
+    let mut multi = MultiCollision::with_rng(rng, f, f.iv(), bits / 2);
+    loop {
+        let mut seen = HashMap::new();
+        for m in multi.messages() {
+            let state = chain(g, &m);
+            if let Some(other) = seen.insert(state, m.clone()) {
+                return (m, other);
+            }
+        }
+        // unlucky, twice as many messages next time
+        multi.extend_with(rng, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use crate::{
+        merkle_damgard::{digest, AesHash},
+        random::seeded,
+    };
+
+    use super::*;
+
+    // counts the calls to the compression function
+    #[derive(Clone)]
+    struct Counted<C> {
+        c: C,
+        calls: Rc<Cell<usize>>,
+    }
+
+    impl<C: Compress> Compress for Counted<C> {
+        type State = C::State;
+        const BLOCK_SIZE: usize = C::BLOCK_SIZE;
+        const BIG_ENDIAN: bool = C::BIG_ENDIAN;
+        const LENGTH_SIZE: usize = C::LENGTH_SIZE;
+
+        fn iv(&self) -> C::State {
+            self.c.iv()
+        }
+        fn compress(&self, state: &mut C::State, block: &[u8]) {
+            self.calls.set(self.calls.get() + 1);
+            self.c.compress(state, block)
+        }
+        fn to_bytes(&self, state: &C::State) -> Vec<u8> {
+            self.c.to_bytes(state)
+        }
+        fn state_from_bytes(&self, bytes: &[u8]) -> C::State {
+            self.c.state_from_bytes(bytes)
+        }
+    }
+
+    #[test]
+    fn test_multicollision() {
+        let f = Counted {
+            c: AesHash::<2>::default(),
+            calls: Rc::new(Cell::new(0)),
+        };
+        let multi = MultiCollision::with_rng(&mut seeded(52), &f, f.iv(), 5);
+        // a birthday attack on 16 bits takes a few hundred blocks
+        assert!(f.calls.get() < 5 * 2000);
+
+        let messages = multi.messages().collect::<Vec<_>>();
+        assert_eq!(messages.len(), 32);
+        for (i, m) in messages.iter().enumerate() {
+            assert!(messages[..i].iter().all(|other| other != m));
+            assert_eq!(digest(&f, m), digest(&f, &messages[0]));
+        }
+    }
+
+    #[test]
+    fn test_cascade_collision() {
+        let f = AesHash::<2>::default();
+        let g = AesHash::<3>::new(*b"giv");
+        let (m1, m2) = cascade_collision(&f, &g);
+        assert_ne!(m1, m2);
+        assert_eq!(digest(&f, &m1), digest(&f, &m2));
+        assert_eq!(digest(&g, &m1), digest(&g, &m2));
+
+        // the same seed builds the same multicollision
+        let pair = cascade_collision_with(&mut seeded(52), &f, &g);
+        assert_eq!(cascade_collision_with(&mut seeded(52), &f, &g), pair);
+    }
+}
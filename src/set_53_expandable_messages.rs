@@ -0,0 +1,171 @@
+#![allow(unused)]
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+    merkle_damgard::{collide_states_with, Compress, MerkleDamgard},
+    random::{os, RandomSource},
+};
+
+fn compress_blocks<C: Compress>(c: &C, mut state: C::State, message: &[u8]) -> C::State {
+    for block in message.chunks(C::BLOCK_SIZE) {
+        c.compress(&mut state, block);
+    }
+    state
+}
+
+// a single block and a message of `n` dummy blocks plus one, both leading from
+// `state` to the returned state
+fn collide_lengths<C>(
+    rng: &mut impl RandomSource,
+    c: &C,
+    state: C::State,
+    n: usize,
+) -> (Vec<u8>, Vec<u8>, C::State)
+where
+    C: Compress,
+    C::State: Hash + Eq,
+{
+    let dummy = vec![0; n * C::BLOCK_SIZE];
+    let long_state = compress_blocks(c, state, &dummy);
+    let (short, long, s) = collide_states_with(rng, c, state, long_state);
+    (short, [dummy, long].concat(), s)
+}
+
+// A (k, k + 2^k - 1)-expandable message: piece i is either one block or
+// 2^i + 1 blocks long and both choices end in the same state, so a message of
+// any length in that range reaches `state`.
+pub struct ExpandableMessage<C: Compress> {
+    pub k: usize,
+    pieces: Vec<(Vec<u8>, Vec<u8>)>,
+    pub state: C::State,
+}
+
+impl<C> ExpandableMessage<C>
+where
+    C: Compress,
+    C::State: Hash + Eq,
+{
+    pub fn new(c: &C, iv: C::State, k: usize) -> Self {
+        Self::with_rng(&mut os(), c, iv, k)
+    }
+    pub fn with_rng(rng: &mut impl RandomSource, c: &C, iv: C::State, k: usize) -> Self {
+        let mut state = iv;
+        let mut pieces = vec![];
+        for i in (0..k).rev() {
+            let (short, long, next) = collide_lengths(rng, c, state, 1 << i);
+            pieces.push((short, long));
+            state = next;
+        }
+        Self { k, pieces, state }
+    }
+    // the message of `len` blocks
+    pub fn message(&self, len: usize) -> Vec<u8> {
+        assert!((self.k..self.k + (1 << self.k)).contains(&len));
+        let extra = len - self.k;
+        self.pieces
+            .iter()
+            .enumerate()
+            .flat_map(|(j, (short, long))| {
+                // the pieces are stored from the longest one down
+                if extra >> (self.k - 1 - j) & 1 == 1 {
+                    long
+                } else {
+                    short
+                }
+            })
+            .copied()
+            .collect()
+    }
+}
+
+// A different message with the same hash as `message`, which should be about
+// 2^k blocks long. A bridge block links the end of an expandable message to one
+// of the intermediate states of `message`, the expandable message is then cut
+// to the length that makes the forgery as long as the original.
+pub fn second_preimage<C>(c: &C, message: &[u8], k: usize) -> Vec<u8>
+where
+    C: Compress,
+    C::State: Hash + Eq,
+{
+    second_preimage_with(&mut os(), c, message, k)
+}
+
+pub fn second_preimage_with<C>(
+    rng: &mut impl RandomSource,
+    c: &C,
+    message: &[u8],
+    k: usize,
+) -> Vec<u8>
+where
+    C: Compress,
+    C::State: Hash + Eq,
+{
+    // the state after block j, the bridge replaces that block so the
+    // expandable message has to be j blocks long
+    let mut state = c.iv();
+    let mut states = HashMap::new();
+    for (j, block) in message.chunks_exact(C::BLOCK_SIZE).enumerate() {
+        c.compress(&mut state, block);
+        if (k..k + (1 << k)).contains(&j) {
+            states.entry(state).or_insert(j);
+        }
+    }
+    assert!(!states.is_empty(), "the message is too short");
+
+    let expandable = ExpandableMessage::with_rng(rng, c, c.iv(), k);
+    loop {
+        let bridge = rng.bytes(C::BLOCK_SIZE);
+        let mut s = expandable.state;
+        c.compress(&mut s, &bridge);
+        if let Some(&j) = states.get(&s) {
+            let forged = [
+                expandable.message(j),
+                bridge,
+                message[(j + 1) * C::BLOCK_SIZE..].to_vec(),
+            ]
+            .concat();
+            if forged != message {
+                return forged;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        merkle_damgard::{digest, AesHash},
+        random::seeded,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_expandable_message() {
+        let c = AesHash::<2>::default();
+        let expandable = ExpandableMessage::new(&c, c.iv(), 4);
+        for len in 4..4 + 16 {
+            let m = expandable.message(len);
+            assert_eq!(m.len(), len * 16);
+            assert_eq!(compress_blocks(&c, c.iv(), &m), expandable.state);
+        }
+    }
+
+    #[test]
+    fn test_second_preimage() {
+        let k = 8;
+        let mut rng = seeded(53);
+        let message = rng.bytes((1 << k) * 16 + 5);
+
+        let c = AesHash::<2>::default();
+        let forged = second_preimage_with(&mut rng, &c, &message, k);
+        assert_ne!(forged, message);
+        assert_eq!(forged.len(), message.len());
+        assert_eq!(digest(&c, &forged), digest(&c, &message));
+
+        let c = AesHash::<3>::new(*b"iv3");
+        let forged = second_preimage(&c, &message, k);
+        assert_ne!(forged, message);
+        assert_eq!(digest(&c, &forged), digest(&c, &message));
+    }
+}
@@ -0,0 +1,234 @@
+#![allow(unused)]
+use std::{
+    collections::HashMap,
+    fs,
+    hash::Hash,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::{
+    merkle_damgard::{collide_states_with, Compress, MerkleDamgard},
+    misc::{from_hex, to_hex},
+    random::{os, RandomSource},
+};
+
+// A binary tree of collisions: node i of a level and its sibling i ^ 1 are
+// taken to node i / 2 of the next level by `blocks[level][i]`. Any of the 2^k
+// leaves reaches the root after k blocks.
+pub struct Diamond<C: Compress> {
+    pub k: usize,
+    leaves: Vec<C::State>,
+    blocks: Vec<Vec<Vec<u8>>>,
+    pub root: C::State,
+    // the number of prefix blocks the published hash was computed for
+    committed: Option<usize>,
+}
+
+impl<C> Diamond<C>
+where
+    C: Compress,
+    C::State: Hash + Eq,
+{
+    // the leaves are random states, they don't have to be reachable from the iv
+    pub fn new(c: &C, k: usize) -> Self {
+        Self::with_rng(&mut os(), c, k)
+    }
+    pub fn with_rng(rng: &mut impl RandomSource, c: &C, k: usize) -> Self {
+        let leaves = (0..1 << k)
+            .map(|_| c.state_from_bytes(&rng.bytes(c.to_bytes(&c.iv()).len())))
+            .collect::<Vec<_>>();
+        let mut level = leaves.clone();
+        let mut blocks = vec![];
+        for _ in 0..k {
+            let mut next = vec![];
+            let mut level_blocks = vec![];
+            for pair in level.chunks(2) {
+                let (b1, b2, s) = collide_states_with(rng, c, pair[0], pair[1]);
+                level_blocks.extend([b1, b2]);
+                next.push(s);
+            }
+            blocks.push(level_blocks);
+            level = next;
+        }
+        Self {
+            k,
+            leaves,
+            blocks,
+            root: level[0],
+            committed: None,
+        }
+    }
+
+    // the hash to publish for messages with a prefix of `prefix_len` bytes,
+    // padded to a block boundary
+    pub fn commit(&mut self, c: &C, prefix_len: usize) -> Vec<u8> {
+        let prefix_blocks = prefix_len.div_ceil(C::BLOCK_SIZE);
+        self.committed = Some(prefix_blocks);
+        let len = (prefix_blocks + 1 + self.k) * C::BLOCK_SIZE;
+        MerkleDamgard::with_state(c.clone(), self.root, len as u64).finalize()
+    }
+
+    // `prefix` padded with zeros, a linking block into one of the leaves and
+    // the path from that leaf to the root, which only has the committed hash
+    // if the prefix pads to as many blocks as the committed one
+    pub fn herd(&self, c: &C, prefix: &[u8]) -> Vec<u8> {
+        self.herd_with(&mut os(), c, prefix)
+    }
+    pub fn herd_with(&self, rng: &mut impl RandomSource, c: &C, prefix: &[u8]) -> Vec<u8> {
+        let prefix_blocks = prefix.len().div_ceil(C::BLOCK_SIZE);
+        assert_eq!(
+            self.committed,
+            Some(prefix_blocks),
+            "the prefix doesn't match the committed length"
+        );
+        let mut message = prefix.to_vec();
+        message.resize(prefix_blocks * C::BLOCK_SIZE, 0);
+        let mut state = c.iv();
+        for block in message.chunks(C::BLOCK_SIZE) {
+            c.compress(&mut state, block);
+        }
+
+        let leaves: HashMap<_, _> = self
+            .leaves
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (*s, i))
+            .collect();
+        let mut i = loop {
+            let link = rng.bytes(C::BLOCK_SIZE);
+            let mut s = state;
+            c.compress(&mut s, &link);
+            if let Some(&i) = leaves.get(&s) {
+                message.extend(link);
+                break i;
+            }
+        };
+        for level in &self.blocks {
+            message.extend(&level[i]);
+            i /= 2;
+        }
+        message
+    }
+
+    // k, then the leaves and the blocks of every level, one per line in hex
+    pub fn save(&self, c: &C, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut lines = vec![self.k.to_string()];
+        lines.extend(self.leaves.iter().map(|s| to_hex(&c.to_bytes(s))));
+        lines.extend(self.blocks.iter().flatten().map(|b| to_hex(b)));
+        fs::write(path, lines.join("\n"))
+    }
+
+    pub fn load(c: &C, path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(ErrorKind::InvalidData, msg.to_string());
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines();
+        let k: usize = lines
+            .next()
+            .and_then(|l| l.parse().ok())
+            .filter(|k| *k < 32)
+            .ok_or(invalid("bad width"))?;
+        // 2^k leaves and 2^(k + 1) - 2 blocks
+        if text.lines().count() != 1 + (1 << k) + (2 << k) - 2 {
+            return Err(invalid("bad line count"));
+        }
+        let mut next = |len: usize| {
+            lines
+                .next()
+                .and_then(from_hex)
+                .filter(|v| v.len() == len)
+                .ok_or(invalid("bad line"))
+        };
+        let state_len = c.to_bytes(&c.iv()).len();
+        let mut leaves = vec![];
+        for _ in 0..1 << k {
+            leaves.push(c.state_from_bytes(&next(state_len)?));
+        }
+        let mut blocks = vec![];
+        for level in 0..k {
+            let mut level_blocks = vec![];
+            for _ in 0..1 << (k - level) {
+                level_blocks.push(next(C::BLOCK_SIZE)?);
+            }
+            blocks.push(level_blocks);
+        }
+
+        // walk one leaf up to find the root, and make sure the blocks collide
+        let mut level = leaves.clone();
+        for level_blocks in &blocks {
+            let mut next = vec![];
+            for (i, pair) in level.chunks(2).enumerate() {
+                let (mut s1, mut s2) = (pair[0], pair[1]);
+                c.compress(&mut s1, &level_blocks[2 * i]);
+                c.compress(&mut s2, &level_blocks[2 * i + 1]);
+                if s1 != s2 {
+                    return Err(invalid("the blocks don't collide"));
+                }
+                next.push(s1);
+            }
+            level = next;
+        }
+        Ok(Self {
+            k,
+            leaves,
+            blocks,
+            root: level[0],
+            committed: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        merkle_damgard::{digest, AesHash},
+        random::seeded,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_herding() {
+        let results = [
+            b"Giants 3 Dodgers 1, Mets 2 Cubs 7, Red Sox 5 Yankees 4",
+            b"Giants 0 Dodgers 2, Mets 4 Cubs 1, Red Sox 3 Yankees 9",
+        ];
+        let c = AesHash::<2>::default();
+        let mut rng = seeded(54);
+        let mut diamond = Diamond::with_rng(&mut rng, &c, 6);
+        let prediction = diamond.commit(&c, results[0].len());
+
+        let path = std::env::temp_dir().join(format!("set_54_diamond_{}.txt", std::process::id()));
+        diamond.save(&c, &path).unwrap();
+        let mut diamond = Diamond::load(&c, &path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(diamond.commit(&c, results[0].len()), prediction);
+
+        for prefix in results {
+            let message = diamond.herd_with(&mut rng, &c, prefix);
+            assert!(message.starts_with(prefix));
+            assert_eq!(digest(&c, &message), prediction);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "committed length")]
+    fn test_herd_longer_prefix() {
+        let c = AesHash::<2>::default();
+        let mut diamond = Diamond::new(&c, 2);
+        diamond.commit(&c, 16);
+        diamond.herd(&c, &[0; 17]);
+    }
+
+    #[test]
+    fn test_load_invalid() {
+        let c = AesHash::<2>::default();
+        let path = std::env::temp_dir().join(format!("set_54_invalid_{}.txt", std::process::id()));
+        for text in ["64\n", "3\n0000\n0000", "x"] {
+            fs::write(&path, text).unwrap();
+            let err = Diamond::load(&c, &path).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
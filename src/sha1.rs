@@ -1,6 +1,8 @@
 #![allow(unused)]
 use itertools::Itertools;
 
+use crate::merkle_damgard::{md_padding, Compress, MerkleDamgard};
+
 const H0: u32 = 0x67452301;
 const H1: u32 = 0xEFCDAB89;
 const H2: u32 = 0x98BADCFE;
@@ -8,65 +10,187 @@ const H3: u32 = 0x10325476;
 const H4: u32 = 0xC3D2E1F0;
 
 pub fn sha1(message: &[u8]) -> [u8; 20] {
-    let mut message = message.to_vec();
-    let ml = message.len() * 8;
-    if ml % 8 == 0 {
-        message.push(0x80);
-    }
-    while (message.len() * 8) % 512 != 448 {
-        message.push(0x00);
-    }
-    for byte in ml.to_be_bytes() {
-        message.push(byte);
-    }
-    assert!((message.len() * 8) % 512 == 0);
-
-    let mut h = [H0, H1, H2, H3, H4];
-    for chunk in message.chunks(64) {
-        let mut w = chunk
-            .chunks(4)
-            .map(|c| u32::from_be_bytes(c.try_into().unwrap()))
-            .collect_vec();
-        for i in 16..80 {
-            w.push((w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1));
-        }
-        let h_origin = h.clone();
-        for i in 0..80 {
-            let (f, k) = match i {
-                0..=19 => ((h[1] & h[2]) | (!h[1] & h[3]), 0x5A827999),
-                20..=39 => (h[1] ^ h[2] ^ h[3], 0x6ED9EBA1),
-                40..=59 => ((h[1] & h[2]) | (h[1] & h[3]) | (h[2] & h[3]), 0x8F1BBCDC),
-                60..=79 => (h[1] ^ h[2] ^ h[3], 0xCA62C1D6),
-                _ => unreachable!(),
-            };
-
-            h[4] = (h[0].rotate_left(5))
-                .wrapping_add(f)
-                .wrapping_add(h[4])
-                .wrapping_add(k)
-                .wrapping_add(w[i]);
-            h[1] = h[1].rotate_left(30);
-            h.rotate_right(1);
-        }
-        for (i, v) in h_origin.iter().enumerate() {
-            h[i] = h[i].wrapping_add(*v);
-        }
+    let mut hasher = Sha1::new();
+    hasher.update(message);
+    hasher.finalize()
+}
+
+// the glue padding appended to a message of `len` bytes
+pub fn sha1_padding(len: usize) -> Vec<u8> {
+    md_padding(len, 64, 8, true)
+}
+
+pub fn digest_to_state(digest: &[u8; 20]) -> [u32; 5] {
+    let mut h = [0; 5];
+    for (i, c) in digest.chunks(4).enumerate() {
+        h[i] = u32::from_be_bytes(c.try_into().unwrap());
+    }
+    h
+}
+
+// continue hashing `message` from state `h`, assuming `processed` bytes
+// (already padded to a block boundary) have been fed into `h` before
+pub fn sha1_from_state(h: [u32; 5], message: &[u8], processed: usize) -> [u8; 20] {
+    let mut hasher = Sha1::with_state(Variant::SHA1, h, processed as u64);
+    hasher.update(message);
+    hasher.finalize()
+}
+
+// The SHA family the compression function belongs to: SHA-0 lacks the
+// rotation in the message expansion, and both can be cut to fewer steps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Variant {
+    // steps of the compression function, 80 for the full hashes
+    pub rounds: usize,
+    pub rotate: bool,
+}
+
+impl Variant {
+    pub const SHA1: Self = Self {
+        rounds: 80,
+        rotate: true,
+    };
+    pub const SHA0: Self = Self {
+        rounds: 80,
+        rotate: false,
+    };
+    pub fn reduced(self, rounds: usize) -> Self {
+        assert!(rounds <= 80);
+        Self { rounds, ..self }
+    }
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Self::SHA1
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha1Compress {
+    pub variant: Variant,
+}
+
+impl Compress for Sha1Compress {
+    type State = [u32; 5];
+    const BLOCK_SIZE: usize = 64;
+    const BIG_ENDIAN: bool = true;
+
+    fn iv(&self) -> [u32; 5] {
+        [H0, H1, H2, H3, H4]
+    }
+    fn compress(&self, state: &mut [u32; 5], block: &[u8]) {
+        compress_variant(state, block, self.variant)
+    }
+    fn to_bytes(&self, state: &[u32; 5]) -> Vec<u8> {
+        state.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+    fn state_from_bytes(&self, bytes: &[u8]) -> [u32; 5] {
+        digest_to_state(bytes.try_into().unwrap())
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha1(MerkleDamgard<Sha1Compress>);
+
+impl Sha1 {
+    pub fn new() -> Self {
+        Self::with_variant(Variant::SHA1)
+    }
+    pub fn with_variant(variant: Variant) -> Self {
+        Self(MerkleDamgard::new(Sha1Compress { variant }))
+    }
+    pub fn with_state(variant: Variant, h: [u32; 5], len: u64) -> Self {
+        Self(MerkleDamgard::with_state(Sha1Compress { variant }, h, len))
+    }
+    pub fn state(&self) -> [u32; 5] {
+        self.0.state()
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+    pub fn finalize(self) -> [u8; 20] {
+        self.0.finalize().try_into().unwrap()
+    }
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn sha_variant(variant: Variant, message: &[u8]) -> [u8; 20] {
+    let mut hasher = Sha1::with_variant(variant);
+    hasher.update(message);
+    hasher.finalize()
+}
+
+fn expand(chunk: &[u8], variant: Variant) -> [u32; 80] {
+    let mut w = [0; 80];
+    for (v, c) in w.iter_mut().zip(chunk.chunks(4)) {
+        *v = u32::from_be_bytes(c.try_into().unwrap());
+    }
+    for i in 16..80 {
+        let v = w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16];
+        w[i] = if variant.rotate { v.rotate_left(1) } else { v };
+    }
+    w
+}
+
+// step `i` of the compression function on the working state a, b, c, d, e
+fn step(h: &mut [u32; 5], i: usize, w: u32) {
+    let (f, k) = match i {
+        0..=19 => ((h[1] & h[2]) | (!h[1] & h[3]), 0x5A827999),
+        20..=39 => (h[1] ^ h[2] ^ h[3], 0x6ED9EBA1),
+        40..=59 => ((h[1] & h[2]) | (h[1] & h[3]) | (h[2] & h[3]), 0x8F1BBCDC),
+        60..=79 => (h[1] ^ h[2] ^ h[3], 0xCA62C1D6),
+        _ => unreachable!(),
+    };
+
+    h[4] = (h[0].rotate_left(5))
+        .wrapping_add(f)
+        .wrapping_add(h[4])
+        .wrapping_add(k)
+        .wrapping_add(w);
+    h[1] = h[1].rotate_left(30);
+    h.rotate_right(1);
+}
+
+// the working state a, b, c, d, e before the first step and after every
+// step, without the final feed-forward
+pub fn trace(h: &[u32; 5], chunk: &[u8], variant: Variant) -> Vec<[u32; 5]> {
+    let mut h = *h;
+    let mut states = vec![h];
+    for (i, w) in expand(chunk, variant)[..variant.rounds].iter().enumerate() {
+        step(&mut h, i, *w);
+        states.push(h);
+    }
+    states
+}
+
+// the xor difference of the working states of two blocks after every step
+pub fn differences(h: &[u32; 5], chunk1: &[u8], chunk2: &[u8], variant: Variant) -> Vec<[u32; 5]> {
+    trace(h, chunk1, variant)
+        .iter()
+        .zip(trace(h, chunk2, variant))
+        .map(|(s1, s2)| [0, 1, 2, 3, 4].map(|i| s1[i] ^ s2[i]))
+        .collect()
+}
+
+fn compress_variant(h: &mut [u32; 5], chunk: &[u8], variant: Variant) {
+    let mut state = *h;
+    for (i, w) in expand(chunk, variant)[..variant.rounds].iter().enumerate() {
+        step(&mut state, i, *w);
+    }
+    for (v, s) in h.iter_mut().zip(state) {
+        *v = v.wrapping_add(s);
     }
-    [
-        h[0].to_be_bytes(),
-        h[1].to_be_bytes(),
-        h[2].to_be_bytes(),
-        h[3].to_be_bytes(),
-        h[4].to_be_bytes(),
-    ]
-    .concat()
-    .try_into()
-    .unwrap()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::base64::u8_to_base64;
+    use crate::{base64::u8_to_base64, misc::to_hex};
 
     use super::*;
 
@@ -78,11 +202,53 @@ mod tests {
         assert_eq!(res, "2jmj7l5rSw0yVb/vlWAYkK/YBw");
 
         let res = sha1(b"The quick brown fox jumps over the lazy dog");
-        let s: String = res.iter().map(|n| format!("{:02x}", n)).collect();
-        assert_eq!(s, "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12");
+        assert_eq!(to_hex(&res), "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12");
 
         let res = sha1(b"The quick brown fox jumps over the lazy cog");
-        let s: String = res.iter().map(|n| format!("{:02x}", n)).collect();
-        assert_eq!(s, "de9f2c7fd25e1b3afad3e85a0bd17d9b100db4b3");
+        assert_eq!(to_hex(&res), "de9f2c7fd25e1b3afad3e85a0bd17d9b100db4b3");
+
+        let msg = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".repeat(3);
+        let mut hasher = Sha1::new();
+        for chunk in msg.chunks(13) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), sha1(&msg));
+    }
+
+    #[test]
+    fn test_sha0() {
+        let res = sha_variant(Variant::SHA0, b"abc");
+        assert_eq!(to_hex(&res), "0164b8a914cd2a5e74c4f7ff082c4d97f1edf880");
+        assert_eq!(sha_variant(Variant::SHA1, b"abc"), sha1(b"abc"));
+
+        // resuming keeps the variant
+        let msg = [b"a".repeat(64), b"abc".to_vec()].concat();
+        let mut hasher = Sha1::with_variant(Variant::SHA0);
+        hasher.update(&msg[..64]);
+        let mut resumed = Sha1::with_state(Variant::SHA0, hasher.state(), 64);
+        resumed.update(&msg[64..]);
+        assert_eq!(resumed.finalize(), sha_variant(Variant::SHA0, &msg));
+    }
+
+    #[test]
+    fn test_trace() {
+        let block: [u8; 64] = std::array::from_fn(|i| i as u8);
+        let full = trace(&[H0, H1, H2, H3, H4], &block, Variant::SHA1);
+        assert_eq!(full.len(), 81);
+        let reduced = trace(&[H0, H1, H2, H3, H4], &block, Variant::SHA1.reduced(20));
+        assert_eq!(reduced[..], full[..21]);
+
+        // the schedules only differ from word 16 on, used in the 17th step
+        let sha0 = trace(&[H0, H1, H2, H3, H4], &block, Variant::SHA0);
+        assert_eq!(sha0[..17], full[..17]);
+        assert_ne!(sha0[17], full[17]);
+
+        // the top bit of the last word doesn't carry into other bits, it shows up
+        // in the 16th step first
+        let mut other = block;
+        other[60] ^= 0x80;
+        let diff = differences(&[H0, H1, H2, H3, H4], &block, &other, Variant::SHA0);
+        assert!(diff[..16].iter().all(|d| *d == [0; 5]));
+        assert_eq!(diff[16], [1 << 31, 0, 0, 0, 0]);
     }
 }
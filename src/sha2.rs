@@ -0,0 +1,541 @@
+#![allow(unused)]
+use itertools::Itertools;
+
+use crate::merkle_damgard::{md_padding, Compress, MerkleDamgard};
+
+const K256: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+const K512: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+pub const H224: [u32; 8] = [
+    0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+];
+pub const H256: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+pub const H384: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+pub const H512: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+pub fn sha224(message: &[u8]) -> [u8; 28] {
+    let mut hasher = Sha224::new();
+    hasher.update(message);
+    hasher.finalize()
+}
+
+pub fn sha256(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(message);
+    hasher.finalize()
+}
+
+pub fn sha384(message: &[u8]) -> [u8; 48] {
+    let mut hasher = Sha384::new();
+    hasher.update(message);
+    hasher.finalize()
+}
+
+pub fn sha512(message: &[u8]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update(message);
+    hasher.finalize()
+}
+
+// the glue padding of SHA-224 and SHA-256 for a message of `len` bytes
+pub fn sha256_padding(len: usize) -> Vec<u8> {
+    md_padding(len, 64, 8, true)
+}
+
+// the glue padding of SHA-384 and SHA-512, the length takes 128 bits
+pub fn sha512_padding(len: usize) -> Vec<u8> {
+    md_padding(len, 128, 16, true)
+}
+
+fn compress256(h: &mut [u32; 8], chunk: &[u8]) {
+    let mut w = chunk
+        .chunks(4)
+        .map(|c| u32::from_be_bytes(c.try_into().unwrap()))
+        .collect_vec();
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w.push(
+            w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1),
+        );
+    }
+    let mut v = *h;
+    for (k, w) in K256.iter().zip(w) {
+        let [a, b, c, d, e, f, g, hh] = v;
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = hh
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(*k)
+            .wrapping_add(w);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        v = [t1.wrapping_add(t2), a, b, c, d.wrapping_add(t1), e, f, g];
+    }
+    for (h, v) in h.iter_mut().zip(v) {
+        *h = h.wrapping_add(v);
+    }
+}
+
+fn compress512(h: &mut [u64; 8], chunk: &[u8]) {
+    let mut w = chunk
+        .chunks(8)
+        .map(|c| u64::from_be_bytes(c.try_into().unwrap()))
+        .collect_vec();
+    for i in 16..80 {
+        let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+        let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+        w.push(
+            w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1),
+        );
+    }
+    let mut v = *h;
+    for (k, w) in K512.iter().zip(w) {
+        let [a, b, c, d, e, f, g, hh] = v;
+        let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = hh
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(*k)
+            .wrapping_add(w);
+        let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        v = [t1.wrapping_add(t2), a, b, c, d.wrapping_add(t1), e, f, g];
+    }
+    for (h, v) in h.iter_mut().zip(v) {
+        *h = h.wrapping_add(v);
+    }
+}
+
+// the compression function of SHA-224 and SHA-256, which only differ in the
+// initial state and the length of the digest
+#[derive(Clone, Copy, Debug)]
+pub struct Sha256Compress {
+    pub iv: [u32; 8],
+}
+
+impl Compress for Sha256Compress {
+    type State = [u32; 8];
+    const BLOCK_SIZE: usize = 64;
+    const BIG_ENDIAN: bool = true;
+
+    fn iv(&self) -> [u32; 8] {
+        self.iv
+    }
+    fn compress(&self, state: &mut [u32; 8], block: &[u8]) {
+        compress256(state, block)
+    }
+    fn to_bytes(&self, state: &[u32; 8]) -> Vec<u8> {
+        state.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+    fn state_from_bytes(&self, bytes: &[u8]) -> [u32; 8] {
+        let words = bytes
+            .chunks(4)
+            .map(|c| u32::from_be_bytes(c.try_into().unwrap()));
+        words.collect_vec().try_into().unwrap()
+    }
+}
+
+// the same for SHA-384 and SHA-512, on 128 byte blocks
+#[derive(Clone, Copy, Debug)]
+pub struct Sha512Compress {
+    pub iv: [u64; 8],
+}
+
+impl Compress for Sha512Compress {
+    type State = [u64; 8];
+    const BLOCK_SIZE: usize = 128;
+    const BIG_ENDIAN: bool = true;
+    const LENGTH_SIZE: usize = 16;
+
+    fn iv(&self) -> [u64; 8] {
+        self.iv
+    }
+    fn compress(&self, state: &mut [u64; 8], block: &[u8]) {
+        compress512(state, block)
+    }
+    fn to_bytes(&self, state: &[u64; 8]) -> Vec<u8> {
+        state.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+    fn state_from_bytes(&self, bytes: &[u8]) -> [u64; 8] {
+        let words = bytes
+            .chunks(8)
+            .map(|c| u64::from_be_bytes(c.try_into().unwrap()));
+        words.collect_vec().try_into().unwrap()
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha224(MerkleDamgard<Sha256Compress>);
+
+impl Sha224 {
+    pub fn new() -> Self {
+        Self(MerkleDamgard::new(Sha256Compress { iv: H224 }))
+    }
+    pub fn with_state(h: [u32; 8], len: u64) -> Self {
+        Self(MerkleDamgard::with_state(
+            Sha256Compress { iv: H224 },
+            h,
+            len,
+        ))
+    }
+    pub fn state(&self) -> [u32; 8] {
+        self.0.state()
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+    pub fn finalize(self) -> [u8; 28] {
+        self.0.finalize()[..28].try_into().unwrap()
+    }
+}
+
+impl Default for Sha224 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha256(MerkleDamgard<Sha256Compress>);
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Self(MerkleDamgard::new(Sha256Compress { iv: H256 }))
+    }
+    pub fn with_state(h: [u32; 8], len: u64) -> Self {
+        Self(MerkleDamgard::with_state(
+            Sha256Compress { iv: H256 },
+            h,
+            len,
+        ))
+    }
+    pub fn state(&self) -> [u32; 8] {
+        self.0.state()
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+    pub fn finalize(self) -> [u8; 32] {
+        self.0.finalize().try_into().unwrap()
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha384(MerkleDamgard<Sha512Compress>);
+
+impl Sha384 {
+    pub fn new() -> Self {
+        Self(MerkleDamgard::new(Sha512Compress { iv: H384 }))
+    }
+    pub fn with_state(h: [u64; 8], len: u64) -> Self {
+        Self(MerkleDamgard::with_state(
+            Sha512Compress { iv: H384 },
+            h,
+            len,
+        ))
+    }
+    pub fn state(&self) -> [u64; 8] {
+        self.0.state()
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+    pub fn finalize(self) -> [u8; 48] {
+        self.0.finalize()[..48].try_into().unwrap()
+    }
+}
+
+impl Default for Sha384 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+pub struct Sha512(MerkleDamgard<Sha512Compress>);
+
+impl Sha512 {
+    pub fn new() -> Self {
+        Self(MerkleDamgard::new(Sha512Compress { iv: H512 }))
+    }
+    pub fn with_state(h: [u64; 8], len: u64) -> Self {
+        Self(MerkleDamgard::with_state(
+            Sha512Compress { iv: H512 },
+            h,
+            len,
+        ))
+    }
+    pub fn state(&self) -> [u64; 8] {
+        self.0.state()
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+    pub fn finalize(self) -> [u8; 64] {
+        self.0.finalize().try_into().unwrap()
+    }
+}
+
+impl Default for Sha512 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::misc::to_hex;
+
+    use super::*;
+
+    const SHORT_MESSAGES: [&[u8]; 3] = [
+        b"",
+        b"abc",
+        b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+    ];
+    const LONG_MESSAGE: &[u8] = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+    #[test]
+    fn test_sha224() {
+        let expects = [
+            "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f",
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+            "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525",
+        ];
+        for (msg, expect) in SHORT_MESSAGES.iter().zip(expects) {
+            assert_eq!(to_hex(&sha224(msg)), expect);
+        }
+        assert_eq!(
+            to_hex(&sha224(LONG_MESSAGE)),
+            "c97ca9a559850ce97a04a96def6d99a9e0e0e2ab14e6b8df265fc0b3"
+        );
+        assert_eq!(
+            to_hex(&sha224(&[b'a'; 1_000_000])),
+            "20794655980c91d8bbb4c1ea97618a4bf03f42581948b2ee4ee7ad67"
+        );
+    }
+
+    #[test]
+    fn test_sha256() {
+        let expects = [
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ];
+        for (msg, expect) in SHORT_MESSAGES.iter().zip(expects) {
+            assert_eq!(to_hex(&sha256(msg)), expect);
+        }
+        assert_eq!(
+            to_hex(&sha256(LONG_MESSAGE)),
+            "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1"
+        );
+        assert_eq!(
+            to_hex(&sha256(&[b'a'; 1_000_000])),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+
+    #[test]
+    fn test_sha384() {
+        let expects = [
+            "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b",
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+            "3391fdddfc8dc7393707a65b1b4709397cf8b1d162af05abfe8f450de5f36bc6b0455a8520bc4e6f5fe95b1fe3c8452b",
+        ];
+        for (msg, expect) in SHORT_MESSAGES.iter().zip(expects) {
+            assert_eq!(to_hex(&sha384(msg)), expect);
+        }
+        assert_eq!(
+            to_hex(&sha384(LONG_MESSAGE)),
+            "09330c33f71147e83d192fc782cd1b4753111b173b3b05d22fa08086e3b0f712fcc7c71a557e2db966c3e9fa91746039"
+        );
+        assert_eq!(
+            to_hex(&sha384(&[b'a'; 1_000_000])),
+            "9d0e1809716474cb086e834e310a4a1ced149e9c00f248527972cec5704c2a5b07b8b3dc38ecc4ebae97ddd87f3d8985"
+        );
+    }
+
+    #[test]
+    fn test_sha512() {
+        let expects = [
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e",
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            "204a8fc6dda82f0a0ced7beb8e08a41657c16ef468b228a8279be331a703c33596fd15c13b1b07f9aa1d3bea57789ca031ad85c7a71dd70354ec631238ca3445",
+        ];
+        for (msg, expect) in SHORT_MESSAGES.iter().zip(expects) {
+            assert_eq!(to_hex(&sha512(msg)), expect);
+        }
+        assert_eq!(
+            to_hex(&sha512(LONG_MESSAGE)),
+            "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+        );
+        assert_eq!(
+            to_hex(&sha512(&[b'a'; 1_000_000])),
+            "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973ebde0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b"
+        );
+    }
+
+    #[test]
+    fn test_streaming_and_state() {
+        let mut hasher = Sha256::new();
+        for chunk in LONG_MESSAGE.chunks(5) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), sha256(LONG_MESSAGE));
+
+        let mut hasher = Sha512::new();
+        for chunk in LONG_MESSAGE.chunks(5) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), sha512(LONG_MESSAGE));
+        // the compression functions plug into the generic construction
+        let c = Sha512Compress { iv: H512 };
+        assert_eq!(
+            crate::merkle_damgard::digest(&c, LONG_MESSAGE),
+            sha512(LONG_MESSAGE)
+        );
+
+        // resume from the state after the first block
+        let mut hasher = Sha256::new();
+        hasher.update(&LONG_MESSAGE[..64]);
+        let mut resumed = Sha256::with_state(hasher.state(), 64);
+        resumed.update(&LONG_MESSAGE[64..]);
+        assert_eq!(resumed.finalize(), sha256(LONG_MESSAGE));
+
+        let mut hasher = Sha384::new();
+        hasher.update(&[b'a'; 128]);
+        let mut resumed = Sha384::with_state(hasher.state(), 128);
+        resumed.update(b"bc");
+        assert_eq!(
+            resumed.finalize(),
+            sha384(&[&[b'a'; 128][..], b"bc"].concat())
+        );
+    }
+}